@@ -1,12 +1,135 @@
-from typing import Any
+from typing import Any, Dict, Iterable, Iterator, List, Optional, Tuple, Union
+
+Point = Tuple[float, float]
+# A box is either a Box or ((x0, y0), (x1, y1)) with x0 < x1 and y0 < y1
+BoxLike = Union["Box", Tuple[Point, Point]]
+
+# Axis-aligned box with named coordinates
+class Box:
+    x0: float
+    y0: float
+    x1: float
+    y1: float
+    def __init__(self, x0: float, y0: float, x1: float, y1: float) -> None: ...
+    @property
+    def lo(self) -> Point: ...
+    @property
+    def hi(self) -> Point: ...
+    @property
+    def width(self) -> float: ...
+    @property
+    def height(self) -> float: ...
+    @property
+    def area(self) -> float: ...
+    def to_tuple(self) -> Tuple[Point, Point]: ...
+    def __repr__(self) -> str: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __ne__(self, other: object) -> bool: ...
 
 class PyOcclusionBuffer:
-    @classmethod
-    def __init__(cls,bot:Tuple[float,float], top:Tuple[float,float]) -> None: ...
-    def add_box_set(self, boxes:List[Tuple[Tuple[float,float],Tuple[float,float]]]) -> None: ...
-    def add_last_box(self)->None: ...
-    
-    # Returns True when box is partially visible, and False if occluded
-    def check_a_box(self,box:Tuple[Tuple[float,float],Tuple[float,float]]) -> bool: ...
-    
+    # strategy is one of "guillotine" (default), "vertical", "horizontal", "max_rect", "min_fragments"
+    def __init__(self, bot: Point, top: Point, strategy: Optional[str] = None) -> None: ...
     def copy(self) -> PyOcclusionBuffer: ...
+    def __copy__(self) -> PyOcclusionBuffer: ...
+    def __deepcopy__(self, memo: Dict[int, Any]) -> PyOcclusionBuffer: ...
+    # Number of live free space fragments
+    def __len__(self) -> int: ...
+    def __repr__(self) -> str: ...
+    @property
+    def bounds(self) -> Tuple[Point, Point]: ...
+    @property
+    def strategy(self) -> str: ...
+
+    # Returns True when box is partially visible, and False if occluded
+    def check_a_box(self, new: BoxLike) -> bool: ...
+    # Payloads (any Python objects) are remembered with the boxes that get added, see covering and occluders
+    def add_last_box(self, payload: Optional[Any] = None) -> None: ...
+    def add_box_set(self, boxes: List[BoxLike], payloads: Optional[List[Any]] = None) -> None: ...
+    # Lazily culls boxes in order, yielding (index, visible) per box; visible boxes are added.
+    # Boxes are pulled chunk_size at a time and processed with the GIL released.
+    def cull_iter(self, boxes: Iterable[BoxLike], chunk_size: int = 256) -> CullIterator: ...
+    # Payloads of occluders sharing area with the box, in insertion order
+    def covering(self, b: BoxLike) -> List[Any]: ...
+    # Occluders added with a payload, only those entirely within region if given
+    def occluders(self, region: Optional[BoxLike] = None) -> List[Tuple[Tuple[Point, Point], Any]]: ...
+
+    # Fragmentation statistics: fragments, dead_slots, free_area, largest_fragment, cuts, next_index,
+    # plus queries, visible and occluded when counters are enabled
+    def stats(self) -> Dict[str, float]: ...
+    def enable_counters(self, enable: bool = True) -> None: ...
+    def reset(self) -> None: ...
+    # Occluders with payloads are clipped to bounds by set_bounds, translate and scale, those leaving them are dropped.
+    def set_bounds(self, bot: Point, top: Point) -> None: ...
+    # Panning and zoom: occluders move, bounds stay in place.
+    def translate(self, offset: Point) -> None: ...
+    def scale(self, center: Point, factors: Point) -> None: ...
+
+    # Rendering needs the plot feature (on in maturin builds), without it these raise NotImplementedError
+    # Svg of free space, occluders and query boxes fitted to buffer bounds
+    def export_svg(self, path: str, occluders: List[BoxLike] = [], queries: List[BoxLike] = [],
+                   size: Tuple[int, int] = (512, 512), labels: bool = False) -> None: ...
+    def export_png(self, path: str, occluders: List[BoxLike] = [], queries: List[BoxLike] = [],
+                   size: Tuple[int, int] = (512, 512), labels: bool = False) -> None: ...
+    # RGBA image with transparent background as numpy array of shape (height, width, 4), dtype uint8
+    def render_rgba(self, occluders: List[BoxLike] = [], queries: List[BoxLike] = [],
+                    size: Tuple[int, int] = (512, 512), labels: bool = False) -> Any: ...
+    # Records queries and insertions; export_trace writes an html page with a slider for .html paths, animated svg otherwise
+    def start_trace(self) -> None: ...
+    def export_trace(self, path: str, frame_seconds: float = 0.5, size: Tuple[int, int] = (512, 512),
+                     labels: bool = False) -> None: ...
+
+    # Operation log for reproducing crashes: recording() returns text accepted by replay()
+    def start_recording(self) -> None: ...
+    def recording(self) -> Optional[str]: ...
+    @staticmethod
+    def replay(log: str) -> PyOcclusionBuffer: ...
+
+    # Live free space fragments and the occupied rest of the bounds; arrays are float32 of shape (N, 4): x0, y0, x1, y1
+    def free_space(self) -> List[Tuple[Point, Point]]: ...
+    def free_space_array(self) -> Any: ...
+    def occupied(self) -> List[Tuple[Point, Point]]: ...
+    def occupied_array(self) -> Any: ...
+
+    # GIS exchange; imported polygons must be axis-aligned rectangles without holes
+    def to_geojson(self, occupied: bool = False) -> str: ...
+    def to_wkt(self) -> str: ...
+    def occupied_wkt(self) -> str: ...
+    @staticmethod
+    def from_geojson(text: str, strategy: Optional[str] = None) -> PyOcclusionBuffer: ...
+    @staticmethod
+    def from_wkt(text: str, bounds: Optional[BoxLike] = None, strategy: Optional[str] = None) -> PyOcclusionBuffer: ...
+
+    def compact(self) -> None: ...
+    # Fraction of deleted fragments that triggers compaction, None disables it
+    def set_compaction_threshold(self, threshold: Optional[float]) -> None: ...
+
+
+# Boxes pulled before one that fails to convert (or before boxes raising) are yielded first,
+# then the error is raised and the iterator is finished.
+class CullIterator(Iterator[Tuple[int, bool]]):
+    def __iter__(self) -> CullIterator: ...
+    def __next__(self) -> Tuple[int, bool]: ...
+
+
+# Versioned scene file: bounds, occluders (id, box, depth, role) and query boxes.
+# Files ending with .json are JSON, anything else is the compact binary form.
+class Scene:
+    def __init__(self, bot: Point, top: Point) -> None: ...
+    @staticmethod
+    def load(path: str) -> Scene: ...
+    def save(self, path: str) -> None: ...
+    @staticmethod
+    def from_json(text: str) -> Scene: ...
+    def to_json(self) -> str: ...
+    @property
+    def version(self) -> int: ...
+    @property
+    def bounds(self) -> Tuple[Point, Point]: ...
+    # role is "object" (checked, added when visible) or "occluder" (always added, not reported)
+    def add_occluder(self, id: int, bot: Point, top: Point, depth: float = 0.0, role: str = "object") -> None: ...
+    def add_query(self, id: int, bot: Point, top: Point) -> None: ...
+    def occluders(self) -> List[Tuple[int, Point, Point, float, str]]: ...
+    def queries(self) -> List[Tuple[int, Point, Point]]: ...
+    # Occluders front to back by depth, then queries; returns buffer and {id: visible}.
+    # Boxes may touch the bounds; raises ValueError if a box crosses them.
+    def run(self, strategy: Optional[str] = None) -> Tuple[PyOcclusionBuffer, Dict[int, bool]]: ...
@@ -0,0 +1,147 @@
+use aabb_occlusion_culling::*;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+use common::XorShift;
+
+/// All scenes live within these bounds, strictly inside so check_a_box accepts them
+const SIZE: f32 = 64.0;
+
+/// Box of random size within [min_size, max_size] placed randomly within the scene
+fn rect(rng: &mut XorShift, min_size: f32, max_size: f32) -> BOX {
+    let w = rng.uniform(min_size, max_size);
+    let h = rng.uniform(min_size, max_size);
+    let x = rng.uniform(1.0, SIZE - 1.0 - w);
+    let y = rng.uniform(1.0, SIZE - 1.0 - h);
+    BOX::new([x, y], [x + w, y + h])
+}
+
+fn bounds() -> BOX {
+    BOX::new([0.0, 0.0], [SIZE, SIZE])
+}
+
+fn random_scene(seed: u64, count: usize, min_size: f32, max_size: f32) -> Vec<BOX> {
+    let mut rng = XorShift(seed);
+    (0..count)
+        .map(|_| rect(&mut rng, min_size, max_size))
+        .collect()
+}
+
+/// Grid of city blocks separated by streets, each holding a few buildings, in random order
+fn city_scene(seed: u64) -> Vec<BOX> {
+    let mut rng = XorShift(seed);
+    let block = 4.0;
+    let mut res = vec![];
+    for bx in 0..15 {
+        for by in 0..15 {
+            let (x0, y0) = (1.5 + bx as f32 * block, 1.5 + by as f32 * block);
+            for _ in 0..4 {
+                let w = rng.uniform(0.5, 2.0);
+                let h = rng.uniform(0.5, 2.0);
+                let x = rng.uniform(x0, x0 + block - 1.0 - w);
+                let y = rng.uniform(y0, y0 + block - 1.0 - h);
+                res.push(BOX::new([x, y], [x + w, y + h]));
+            }
+        }
+    }
+    // shuffle, as buildings come in depth order rather than spatial order
+    for i in (1..res.len()).rev() {
+        let j = rng.uniform(0.0, i as f32) as usize;
+        res.swap(i, j);
+    }
+    res
+}
+
+/// Stacked windows (front first), each with a title bar and rows of widgets
+fn ui_scene(seed: u64) -> Vec<BOX> {
+    let mut rng = XorShift(seed);
+    let mut res = vec![];
+    for _ in 0..12 {
+        let window = rect(&mut rng, 10.0, 30.0);
+        let (x0, y0, x1, y1) = (window.lo(0), window.lo(1), window.hi(0), window.hi(1));
+        // title bar
+        res.push(BOX::new([x0, y1 - 1.0], [x1, y1]));
+        let mut y = y1 - 1.5;
+        while y - 1.0 > y0 {
+            let mut x = x0 + 0.25;
+            while x + 2.0 < x1 {
+                let w = rng.uniform(1.0, 4.0).min(x1 - x - 0.25);
+                res.push(BOX::new([x, y - 1.0], [x + w, y]));
+                x += w + 0.25;
+            }
+            y -= 1.25;
+        }
+        res.push(window);
+    }
+    res
+}
+
+fn fill(scene: &[BOX]) -> OcclusionBuffer {
+    let mut ob = OcclusionBuffer::new(bounds());
+    ob.add_box_set(scene.to_vec());
+    ob
+}
+
+fn bench_insertion(c: &mut Criterion, name: &str, base: &OcclusionBuffer, scene: &[BOX]) {
+    let mut res = base.clone();
+    res.add_box_set(scene.to_vec());
+    println!(
+        "{name}: {} boxes, {} -> {} fragments",
+        scene.len(),
+        base.free_boxes().count(),
+        res.free_boxes().count()
+    );
+    c.bench_function(name, |b| {
+        b.iter_batched(
+            || (base.clone(), scene.to_vec()),
+            |(mut ob, boxes)| {
+                ob.add_box_set(boxes);
+                ob
+            },
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn insertion(c: &mut Criterion) {
+    let empty = OcclusionBuffer::new(bounds());
+    bench_insertion(
+        c,
+        "many_small_occluders",
+        &empty,
+        &random_scene(1, 2000, 0.2, 1.0),
+    );
+    bench_insertion(
+        c,
+        "few_large_occluders",
+        &empty,
+        &random_scene(2, 20, 10.0, 30.0),
+    );
+    let fragmented = fill(&random_scene(3, 3000, 0.05, 0.2));
+    bench_insertion(
+        c,
+        "fragmented_free_space",
+        &fragmented,
+        &random_scene(4, 200, 1.0, 4.0),
+    );
+    bench_insertion(c, "city_blocks", &empty, &city_scene(5));
+    bench_insertion(c, "ui_layout", &empty, &ui_scene(6));
+}
+
+fn queries(c: &mut Criterion) {
+    let mut ob = fill(&random_scene(3, 3000, 0.05, 0.2));
+    let queries = random_scene(7, 1000, 0.1, 2.0);
+    println!("query_throughput: {} fragments", ob.free_boxes().count());
+    c.bench_function("query_throughput", |b| {
+        b.iter(|| {
+            queries
+                .iter()
+                .filter(|&&q| ob.check_a_box(q) == OcclusionStatus::PartiallyVisible)
+                .count()
+        })
+    });
+}
+
+criterion_group!(benches, insertion, queries);
+criterion_main!(benches);
@@ -0,0 +1,14 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "capi")]
+    {
+        println!("cargo:rerun-if-changed=src/capi.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+        // generated into OUT_DIR only, tests/capi.rs checks that include/ is up to date
+        let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let out_dir = std::env::var("OUT_DIR").unwrap();
+        cbindgen::generate(&crate_dir)
+            .expect("Unable to generate C header")
+            .write_to_file(format!("{out_dir}/aabb_occlusion_culling.h"));
+    }
+}
@@ -0,0 +1,55 @@
+use aabb_occlusion_culling::*;
+
+fn floats(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|c| f32::from_le_bytes(c.try_into().unwrap()))
+        .collect()
+}
+
+/// Reads a box from 16 bytes: lo.x, lo.y, hi.x, hi.y as little endian f32.
+/// NaN, infinite and huge coordinates are kept, the buffer has to cope with them.
+/// Returns None only for empty boxes.
+pub fn read_box(bytes: &[u8]) -> Option<BOX> {
+    let v = floats(bytes);
+    let (x0, x1) = (v[0].min(v[2]), v[0].max(v[2]));
+    let (y0, y1) = (v[1].min(v[3]), v[1].max(v[3]));
+    if x0 == x1 || y0 == y1 {
+        return None;
+    }
+    Some(BOX::new([x0, y0], [x1, y1]))
+}
+
+/// Input starts with split strategy byte and the bounds box.
+/// Bounds have to be finite, since free space starts out as the whole bounds.
+pub fn header(data: &[u8]) -> Option<(SplitStrategy, BOX, &[u8])> {
+    if data.len() < 17 || floats(&data[1..17]).iter().any(|x| !x.is_finite()) {
+        return None;
+    }
+    let strategy = SplitStrategy::ALL[data[0] as usize % SplitStrategy::ALL.len()];
+    Some((strategy, read_box(&data[1..17])?, &data[17..]))
+}
+
+pub fn boxes(data: &[u8]) -> impl Iterator<Item = BOX> + '_ {
+    data.chunks_exact(16).filter_map(read_box)
+}
+
+/// Inserts the box through the API matching its position, then checks free space invariants
+pub fn insert_checked(ob: &mut OcclusionBuffer, b: BOX) {
+    if ob.bounds().contains(b) {
+        if ob.check_a_box(b) == OcclusionStatus::PartiallyVisible {
+            ob.add_last_box();
+        }
+    } else {
+        OcclusionQuery::insert(ob, b);
+    }
+
+    let live: Vec<BOX> = ob.free_boxes().copied().collect();
+    for (i, f) in live.iter().enumerate() {
+        assert!(!f.is_empty(), "Empty box {f:?} stored");
+        assert!(!f.overlaps_with(&b), "{f:?} intersects inserted {b:?}");
+        for g in &live[i + 1..] {
+            assert!(!f.overlaps_with(g), "{f:?} intersects {g:?}");
+        }
+    }
+}
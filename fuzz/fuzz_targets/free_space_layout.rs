@@ -0,0 +1,30 @@
+#![no_main]
+//! Inserts a sequence of boxes into a buffer with arbitrary pre-existing free space.
+//! Input: strategy byte, bounds (16 bytes), number of free boxes N (1 byte),
+//! N free boxes (16 bytes each), then boxes to insert (16 bytes each).
+//! Free boxes outside bounds or overlapping earlier ones are dropped.
+use aabb_occlusion_culling::*;
+use libfuzzer_sys::fuzz_target;
+
+mod common;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((strategy, bounds, rest)) = common::header(data) else {
+        return;
+    };
+    let Some((&n, rest)) = rest.split_first() else {
+        return;
+    };
+    let (free_bytes, news) = rest.split_at((n as usize * 16).min(rest.len()));
+
+    let mut free: Vec<BOX> = vec![];
+    for b in common::boxes(free_bytes) {
+        if bounds.encloses(&b) && free.iter().all(|f| !f.overlaps_with(&b)) {
+            free.push(b);
+        }
+    }
+    let mut ob = OcclusionBuffer::from_free_space(bounds, free, strategy);
+    for b in common::boxes(news) {
+        common::insert_checked(&mut ob, b);
+    }
+});
@@ -0,0 +1,17 @@
+#![no_main]
+//! Inserts a sequence of boxes into a fresh buffer.
+//! Input: strategy byte, bounds (16 bytes), then boxes (16 bytes each).
+use aabb_occlusion_culling::*;
+use libfuzzer_sys::fuzz_target;
+
+mod common;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((strategy, bounds, rest)) = common::header(data) else {
+        return;
+    };
+    let mut ob = OcclusionBuffer::with_strategy(bounds, strategy);
+    for b in common::boxes(rest) {
+        common::insert_checked(&mut ob, b);
+    }
+});
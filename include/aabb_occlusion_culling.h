@@ -0,0 +1,126 @@
+#ifndef AABB_OCCLUSION_CULLING_H
+#define AABB_OCCLUSION_CULLING_H
+
+/* Generated with cbindgen from src/capi.rs, do not edit by hand */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+
+
+#define AOC_STRATEGY_GUILLOTINE 0
+
+#define AOC_STRATEGY_VERTICAL 1
+
+#define AOC_STRATEGY_HORIZONTAL 2
+
+#define AOC_STRATEGY_MAX_RECT 3
+
+#define AOC_STRATEGY_MIN_FRAGMENTS 4
+
+/*
+ Result of every call that can fail
+ */
+typedef enum AocStatus {
+  AOC_STATUS_OK = 0,
+  AOC_STATUS_NULL_POINTER = 1,
+  /*
+   Box has NaN coordinates or is empty (lo >= hi on some axis)
+   */
+  AOC_STATUS_INVALID_BOX = 2,
+  /*
+   Output array is smaller than needed, required count is still written
+   */
+  AOC_STATUS_BUFFER_TOO_SMALL = 3,
+  /*
+   Buffer code panicked, destroy the buffer
+   */
+  AOC_STATUS_PANIC = 4,
+} AocStatus;
+
+/*
+ Opaque handle to OcclusionBuffer
+ */
+typedef struct AocBuffer AocBuffer;
+
+/*
+ Axis-aligned box, lo is the bottom left corner
+ */
+typedef struct AocBox {
+  float lo[2];
+  float hi[2];
+} AocBox;
+
+/*
+ Creates a buffer with given bounds and one of AOC_STRATEGY_* constants.
+ Returns NULL for invalid bounds or unknown strategy.
+ */
+struct AocBuffer *aoc_buffer_new(struct AocBox bounds, int32_t strategy);
+
+/*
+ Destroys a buffer, NULL is ignored.
+
+ # Safety
+ buffer must come from aoc_buffer_new and must not be used afterwards.
+ */
+void aoc_buffer_free(struct AocBuffer *buffer);
+
+/*
+ Sets *visible to whether any part of the box is free, updating query counters.
+
+ # Safety
+ buffer must come from aoc_buffer_new, visible must point to writable bool.
+ */
+enum AocStatus aoc_query(struct AocBuffer *buffer, struct AocBox b, bool *visible);
+
+/*
+ Marks the box as occupied, it may touch or cross the bounds.
+
+ # Safety
+ buffer must come from aoc_buffer_new.
+ */
+enum AocStatus aoc_insert(struct AocBuffer *buffer, struct AocBox b);
+
+/*
+ Culls count boxes in order (front to back): each box is checked, visible ones are inserted.
+ Visibility of boxes[i] goes to visible[i]. Stops at the first invalid box.
+
+ # Safety
+ buffer must come from aoc_buffer_new, boxes and visible must point to count elements.
+ */
+enum AocStatus aoc_cull_batch(struct AocBuffer *buffer,
+                              const struct AocBox *boxes,
+                              size_t count,
+                              bool *visible);
+
+/*
+ Writes live free space fragments into out. *count receives the number of fragments even if
+ capacity is too small, so calling with capacity 0 asks for the needed size.
+
+ # Safety
+ buffer must come from aoc_buffer_new, out must point to capacity elements (may be NULL
+ when capacity is 0), count must point to writable size_t.
+ */
+enum AocStatus aoc_free_space(const struct AocBuffer *buffer,
+                              struct AocBox *out,
+                              size_t capacity,
+                              size_t *count);
+
+/*
+ Restores full free space.
+
+ # Safety
+ buffer must come from aoc_buffer_new.
+ */
+enum AocStatus aoc_reset(struct AocBuffer *buffer);
+
+/*
+ Static description of a status code. Takes a plain int, so codes that are not
+ AocStatus values (e.g. from a newer header) get "unknown status" instead of undefined behaviour.
+ */
+const char *aoc_status_message(int status);
+
+#endif /* AABB_OCCLUSION_CULLING_H */
@@ -0,0 +1,166 @@
+use crate::box_cutting::*;
+use crate::maxrects::MaxRectsBuffer;
+use crate::oplog::Op;
+use crate::raster::RasterBuffer;
+use crate::{OcclusionBuffer, OcclusionStatus};
+use box_intersect_ze::boxes::BBox;
+
+/// Operations every occlusion buffer supports, so that backends can be swapped
+/// and compared against each other.
+pub trait OcclusionQuery {
+    /// Region covered by the buffer
+    fn bounds(&self) -> BOX;
+    /// Checks if any part of the box is still free. The box may touch or cross the bounds.
+    /// Takes &mut self since OcclusionBuffer keeps the result for add_last_box and counts queries.
+    fn query(&mut self, b: &BOX) -> OcclusionStatus;
+    /// Marks the box as occupied, it may touch or cross the bounds
+    fn insert(&mut self, b: BOX);
+    /// Restores full free space within bounds
+    fn reset(&mut self);
+    /// Fragments of free space, these may overlap depending on the backend
+    fn free_space_iter(&self) -> Box<dyn Iterator<Item = BOX> + '_>;
+}
+
+/// Free space representations usable for placement, not just visibility
+pub trait FreeSpaceBackend: OcclusionQuery {
+    /// Finds a free spot where w×h box fits, if any
+    fn find_fit(&self, w: f32, h: f32) -> Option<BOX>;
+}
+
+fn status(visible: bool) -> OcclusionStatus {
+    match visible {
+        true => OcclusionStatus::PartiallyVisible,
+        false => OcclusionStatus::Occluded,
+    }
+}
+
+/// Selects which free space representation a buffer uses
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackendKind {
+    /// Non-overlapping rectangles (OcclusionBuffer)
+    Disjoint(SplitStrategy),
+    /// Overlapping maximal empty rectangles (MaxRectsBuffer)
+    MaximalRectangles,
+}
+
+impl BackendKind {
+    pub fn build(self, bounds: BOX) -> Box<dyn FreeSpaceBackend> {
+        match self {
+            BackendKind::Disjoint(strategy) => {
+                Box::new(OcclusionBuffer::with_strategy(bounds, strategy))
+            }
+            BackendKind::MaximalRectangles => Box::new(MaxRectsBuffer::new(bounds)),
+        }
+    }
+}
+
+impl OcclusionQuery for OcclusionBuffer {
+    fn bounds(&self) -> BOX {
+        self.bounds()
+    }
+
+    /// Same intersection code, counters and trace as check_a_box, without its strict bounds check.
+    /// Discards the result of a pending check_a_box.
+    fn query(&mut self, b: &BOX) -> OcclusionStatus {
+        self.record(Op::Query(*b));
+        self.run_query(*b)
+    }
+
+    fn insert(&mut self, b: BOX) {
+        // occluders may touch the bounds, so check_a_box can not be used here
+        self.record(Op::Intersect(b));
+        if self.find_intersections(b) == OcclusionStatus::PartiallyVisible {
+            self.add_last_box();
+        }
+    }
+
+    fn reset(&mut self) {
+        OcclusionBuffer::reset(self)
+    }
+
+    fn free_space_iter(&self) -> Box<dyn Iterator<Item = BOX> + '_> {
+        Box::new(self.free_boxes().copied())
+    }
+}
+
+impl FreeSpaceBackend for OcclusionBuffer {
+    /// Only finds spots within a single fragment, even if neighbouring fragments could fit the box together
+    fn find_fit(&self, w: f32, h: f32) -> Option<BOX> {
+        self.free_boxes()
+            .find(|f| f.hi(0) - f.lo(0) >= w && f.hi(1) - f.lo(1) >= h)
+            .map(|f| BOX::new([f.lo(0), f.lo(1)], [f.lo(0) + w, f.lo(1) + h]))
+    }
+}
+
+impl OcclusionQuery for MaxRectsBuffer {
+    fn bounds(&self) -> BOX {
+        self.bounds()
+    }
+
+    fn query(&mut self, b: &BOX) -> OcclusionStatus {
+        status(self.is_visible(b))
+    }
+
+    fn insert(&mut self, b: BOX) {
+        self.occlude(b)
+    }
+
+    fn reset(&mut self) {
+        *self = MaxRectsBuffer::new(self.bounds());
+    }
+
+    fn free_space_iter(&self) -> Box<dyn Iterator<Item = BOX> + '_> {
+        Box::new(self.free_rects().iter().copied())
+    }
+}
+
+impl FreeSpaceBackend for MaxRectsBuffer {
+    fn find_fit(&self, w: f32, h: f32) -> Option<BOX> {
+        self.find_fit(w, h)
+    }
+}
+
+impl From<&OcclusionBuffer> for MaxRectsBuffer {
+    fn from(ob: &OcclusionBuffer) -> Self {
+        let bounds = ob.bounds();
+        let mut res = MaxRectsBuffer::new(bounds);
+        let free: Vec<BOX> = ob.free_boxes().copied().collect();
+        for occupied in complement(&bounds, &free) {
+            res.occlude(occupied);
+        }
+        res
+    }
+}
+
+impl From<&MaxRectsBuffer> for OcclusionBuffer {
+    fn from(mr: &MaxRectsBuffer) -> Self {
+        let bounds = mr.bounds();
+        let mut res = OcclusionBuffer::new(bounds);
+        for occupied in complement(&bounds, mr.free_rects()) {
+            OcclusionQuery::insert(&mut res, occupied);
+        }
+        res
+    }
+}
+
+impl OcclusionQuery for RasterBuffer {
+    fn bounds(&self) -> BOX {
+        self.bounds()
+    }
+
+    fn query(&mut self, b: &BOX) -> OcclusionStatus {
+        status(self.is_visible(b))
+    }
+
+    fn insert(&mut self, b: BOX) {
+        self.occlude(b)
+    }
+
+    fn reset(&mut self) {
+        self.clear()
+    }
+
+    fn free_space_iter(&self) -> Box<dyn Iterator<Item = BOX> + '_> {
+        Box::new(self.free_runs())
+    }
+}
@@ -0,0 +1,156 @@
+//! Runs a scene through OcclusionBuffer and prints per-box visibility, final free space and statistics.
+//!
+//! Usage: occlusion-cli SCENE [--strategy NAME] [--svg PATH] [--no-free-space]
+//!
+//! SCENE is a scene file (.json or binary, see Scene) or a .csv file with one `x0,y0,x1,y1` row per box,
+//! where the first row holds the bounds, optionally preceded by a non-numeric header row. Csv boxes are objects with ids given by their row order.
+//! Boxes have to lie within the bounds, touching or covering them is fine.
+//! Occluders are processed front to back, objects are checked and added when visible,
+//! queries are checked against the final free space.
+use std::process::exit;
+
+use aabb_occlusion_culling::*;
+
+struct Args {
+    scene: String,
+    strategy: SplitStrategy,
+    svg: Option<String>,
+    free_space: bool,
+}
+
+const USAGE: &str = "Usage: occlusion-cli SCENE [--strategy NAME] [--svg PATH] [--no-free-space]";
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = std::env::args().skip(1);
+    let mut res = Args {
+        scene: String::new(),
+        strategy: SplitStrategy::default(),
+        svg: None,
+        free_space: true,
+    };
+    while let Some(a) = args.next() {
+        match a.as_str() {
+            "--strategy" => {
+                res.strategy = args.next().ok_or("--strategy needs a value")?.parse()?;
+            }
+            "--svg" => res.svg = Some(args.next().ok_or("--svg needs a path")?),
+            "--no-free-space" => res.free_space = false,
+            "-h" | "--help" => return Err(USAGE.to_owned()),
+            _ if a.starts_with("--") => return Err(format!("Unknown option {a}\n{USAGE}")),
+            _ => res.scene = a,
+        }
+    }
+    match res.scene.is_empty() {
+        true => Err(USAGE.to_owned()),
+        false => Ok(res),
+    }
+}
+
+fn read_csv(text: &str) -> Result<Scene, String> {
+    let mut rows = vec![];
+    let mut first = true;
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let cells: Vec<&str> = line.split(',').map(str::trim).collect();
+        // a header is only allowed as the first row and must not contain numbers
+        if std::mem::take(&mut first) && cells.iter().all(|c| c.parse::<f32>().is_err()) {
+            continue;
+        }
+        let v: Result<Vec<f32>, _> = cells.iter().map(|c| c.parse::<f32>()).collect();
+        match v {
+            Ok(v) if v.len() == 4 => rows.push(BOX::new([v[0], v[1]], [v[2], v[3]])),
+            _ => {
+                return Err(format!(
+                    "Line {}: expected x0,y0,x1,y1, got {line:?}",
+                    n + 1
+                ))
+            }
+        }
+    }
+    match rows.is_empty() {
+        true => Err("Scene has no bounds row".to_owned()),
+        false => {
+            let mut scene = Scene::new(rows[0]);
+            for (id, b) in rows[1..].iter().enumerate() {
+                scene.occluders.push(SceneOccluder {
+                    id: id as u64,
+                    lo: [b.lo(0), b.lo(1)],
+                    hi: [b.hi(0), b.hi(1)],
+                    depth: 0.0,
+                    role: Role::Object,
+                });
+            }
+            Ok(scene)
+        }
+    }
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let scene = match args.scene.ends_with(".csv") {
+        true => read_csv(
+            &std::fs::read_to_string(&args.scene).map_err(|e| format!("{}: {e}", args.scene))?,
+        )?,
+        false => Scene::load(&args.scene)?,
+    };
+    let (buffer, visibility) = scene.run(args.strategy)?;
+    println!("# visibility");
+    for (id, status) in &visibility {
+        let word = match status {
+            OcclusionStatus::PartiallyVisible => "visible",
+            OcclusionStatus::Occluded => "occluded",
+        };
+        println!("{id} {word}");
+    }
+    if args.free_space {
+        println!("# free space");
+        for b in buffer.free_boxes() {
+            println!("{} {} {} {}", b.lo(0), b.lo(1), b.hi(0), b.hi(1));
+        }
+    }
+    let stats = buffer.stats();
+    println!("# stats");
+    println!("occluders {}", scene.occluders.len());
+    println!("queries {}", scene.queries.len());
+    println!(
+        "visible {}",
+        visibility
+            .iter()
+            .filter(|(_, s)| *s == OcclusionStatus::PartiallyVisible)
+            .count()
+    );
+    println!("fragments {}", stats.fragments);
+    println!("free_area {}", stats.free_area);
+    println!("largest_fragment {}", stats.largest_fragment);
+    println!("cuts {}", stats.cuts);
+    if let Some(path) = &args.svg {
+        write_svg(&scene, &buffer, path)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "plot")]
+fn write_svg(scene: &Scene, buffer: &OcclusionBuffer, path: &str) -> Result<(), String> {
+    let options = PlotOptions {
+        labels: true,
+        ..Default::default()
+    };
+    let occluders: Vec<BOX> = scene.occluders.iter().map(|o| o.bbox()).collect();
+    let queries: Vec<BOX> = scene.queries.iter().map(|q| q.bbox()).collect();
+    export_svg(buffer, &occluders, &queries, path, &options)
+}
+
+#[cfg(not(feature = "plot"))]
+fn write_svg(_scene: &Scene, _buffer: &OcclusionBuffer, _path: &str) -> Result<(), String> {
+    Err("--svg needs occlusion-cli built with the plot feature".to_owned())
+}
+
+fn main() {
+    let res = parse_args().and_then(run);
+    if let Err(e) = res {
+        eprintln!("{e}");
+        exit(1);
+    }
+}
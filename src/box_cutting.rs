@@ -21,6 +21,12 @@ pub trait BoxExtensions {
     fn safe_new(a: [f32; 2], b: [f32; 2]) -> Self;
     /// Checks if a given point is strictly inside a given box
     fn contains_point(&self, v: [f32; 2]) -> bool;
+    /// Area covered by the box (zero for empty and NOWHERE boxes)
+    fn area(&self) -> f32;
+    /// Checks if self and b share some area (touching edges do not count)
+    fn overlaps_with(&self, b: &Self) -> bool;
+    /// Checks if b lies within self, edges are allowed to coincide
+    fn encloses(&self, b: &Self) -> bool;
 }
 
 impl BoxExtensions for BOX {
@@ -57,6 +63,188 @@ impl BoxExtensions for BOX {
     fn is_empty(&self) -> bool {
         (self.lo(0) == self.hi(0)) || (self.lo(1) == self.hi(1))
     }
+
+    fn area(&self) -> f32 {
+        (self.hi(0) - self.lo(0)) * (self.hi(1) - self.lo(1))
+    }
+
+    fn overlaps_with(&self, b: &Self) -> bool {
+        self.lo(0) < b.hi(0) && b.lo(0) < self.hi(0) && self.lo(1) < b.hi(1) && b.lo(1) < self.hi(1)
+    }
+
+    fn encloses(&self, b: &Self) -> bool {
+        self.lo(0) <= b.lo(0)
+            && b.hi(0) <= self.hi(0)
+            && self.lo(1) <= b.lo(1)
+            && b.hi(1) <= self.hi(1)
+    }
+}
+
+/// Defines how the part of a free box that is not covered by a new box gets broken into fragments.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum SplitStrategy {
+    /// Fixed subdivision pattern for every intersection case (original behavior)
+    #[default]
+    Guillotine,
+    /// Full-height strips left and right of the new box, short pieces above and below it
+    VerticalStrips,
+    /// Full-width strips above and below the new box, short pieces left and right of it
+    HorizontalStrips,
+    /// Picks the layout that leaves the largest possible fragment
+    MaxLargestRect,
+    /// Picks the layout with fewest fragments. All layouts are minimal for a single cut,
+    /// so ties are broken by avoiding thin slivers (largest shortest side wins).
+    MinFragments,
+}
+
+impl SplitStrategy {
+    pub const ALL: [SplitStrategy; 5] = [
+        SplitStrategy::Guillotine,
+        SplitStrategy::VerticalStrips,
+        SplitStrategy::HorizontalStrips,
+        SplitStrategy::MaxLargestRect,
+        SplitStrategy::MinFragments,
+    ];
+}
+
+impl std::str::FromStr for SplitStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "guillotine" => Ok(SplitStrategy::Guillotine),
+            "vertical" => Ok(SplitStrategy::VerticalStrips),
+            "horizontal" => Ok(SplitStrategy::HorizontalStrips),
+            "max_rect" => Ok(SplitStrategy::MaxLargestRect),
+            "min_fragments" => Ok(SplitStrategy::MinFragments),
+            other => Err(format!("Unknown split strategy {other:?}")),
+        }
+    }
+}
+
+impl std::fmt::Display for SplitStrategy {
+    /// Same names as accepted by from_str
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SplitStrategy::Guillotine => "guillotine",
+            SplitStrategy::VerticalStrips => "vertical",
+            SplitStrategy::HorizontalStrips => "horizontal",
+            SplitStrategy::MaxLargestRect => "max_rect",
+            SplitStrategy::MinFragments => "min_fragments",
+        })
+    }
+}
+
+/// Breaks free into fragments covering everything except its overlap with cut.
+/// Empty fragments are not returned. Guillotine is treated as VerticalStrips here, since
+/// its per-case patterns live in cut_space.
+pub fn split_remainder(free: &BOX, cut: &BOX, strategy: SplitStrategy) -> Vec<BOX> {
+    let f_lo = [free.lo(0), free.lo(1)];
+    let f_hi = [free.hi(0), free.hi(1)];
+    // overlap of cut with free
+    let i_lo = [cut.lo(0).max(f_lo[0]), cut.lo(1).max(f_lo[1])];
+    let i_hi = [cut.hi(0).min(f_hi[0]), cut.hi(1).min(f_hi[1])];
+    if i_lo[0] >= i_hi[0] || i_lo[1] >= i_hi[1] {
+        // nothing to cut
+        return vec![*free];
+    }
+
+    let vertical = [
+        BOX::new([f_lo[0], f_lo[1]], [i_lo[0], f_hi[1]]),
+        BOX::new([i_hi[0], f_lo[1]], [f_hi[0], f_hi[1]]),
+        BOX::new([i_lo[0], f_lo[1]], [i_hi[0], i_lo[1]]),
+        BOX::new([i_lo[0], i_hi[1]], [i_hi[0], f_hi[1]]),
+    ];
+    let horizontal = [
+        BOX::new([f_lo[0], f_lo[1]], [f_hi[0], i_lo[1]]),
+        BOX::new([f_lo[0], i_hi[1]], [f_hi[0], f_hi[1]]),
+        BOX::new([f_lo[0], i_lo[1]], [i_lo[0], i_hi[1]]),
+        BOX::new([i_hi[0], i_lo[1]], [f_hi[0], i_hi[1]]),
+    ];
+    // "pinwheel" layouts where every fragment owns exactly one corner of free
+    let clockwise = [
+        BOX::new([f_lo[0], f_lo[1]], [i_lo[0], i_hi[1]]),
+        BOX::new([f_lo[0], i_hi[1]], [i_hi[0], f_hi[1]]),
+        BOX::new([i_hi[0], i_lo[1]], [f_hi[0], f_hi[1]]),
+        BOX::new([i_lo[0], f_lo[1]], [f_hi[0], i_lo[1]]),
+    ];
+    let counter_clockwise = [
+        BOX::new([f_lo[0], i_lo[1]], [i_lo[0], f_hi[1]]),
+        BOX::new([i_lo[0], i_hi[1]], [f_hi[0], f_hi[1]]),
+        BOX::new([i_hi[0], f_lo[1]], [f_hi[0], i_hi[1]]),
+        BOX::new([f_lo[0], f_lo[1]], [i_hi[0], i_lo[1]]),
+    ];
+
+    let non_empty =
+        |layout: [BOX; 4]| -> Vec<BOX> { layout.into_iter().filter(|b| !b.is_empty()).collect() };
+    let candidates = || {
+        [vertical, horizontal, clockwise, counter_clockwise]
+            .into_iter()
+            .map(non_empty)
+    };
+
+    match strategy {
+        SplitStrategy::Guillotine | SplitStrategy::VerticalStrips => non_empty(vertical),
+        SplitStrategy::HorizontalStrips => non_empty(horizontal),
+        SplitStrategy::MaxLargestRect => pick_best(candidates(), |layout| {
+            layout.iter().map(|b| b.area()).fold(0.0, f32::max)
+        }),
+        SplitStrategy::MinFragments => pick_best(candidates(), |layout| {
+            let thinnest = layout
+                .iter()
+                .map(|b| (b.hi(0) - b.lo(0)).min(b.hi(1) - b.lo(1)))
+                .fold(f32::INFINITY, f32::min);
+            (-(layout.len() as f32), thinnest)
+        }),
+    }
+}
+
+/// Like box_intersect_ze::intersect_brute_force, but reports positions of the intersecting boxes
+/// in a.boxes and b.boxes instead of their ids
+pub(crate) fn intersect_brute_force_idx(
+    a: &set::BBoxSet<BOX, usize>,
+    b: &set::BBoxSet<BOX, usize>,
+    out: &mut Vec<(usize, usize)>,
+) {
+    for (i, (ba, _)) in a.boxes.iter().enumerate() {
+        for (j, (bb, _)) in b.boxes.iter().enumerate() {
+            if ba.intersects(bb) {
+                out.push((i, j));
+            }
+        }
+    }
+}
+
+/// Produces disjoint boxes covering the part of bounds that none of boxes cover.
+/// Boxes are allowed to overlap each other.
+pub fn complement(bounds: &BOX, boxes: &[BOX]) -> Vec<BOX> {
+    let mut pieces = vec![*bounds];
+    for b in boxes {
+        pieces = pieces
+            .into_iter()
+            .flat_map(|p| match p.overlaps_with(b) {
+                true => split_remainder(&p, b, SplitStrategy::VerticalStrips),
+                false => vec![p],
+            })
+            .collect();
+    }
+    pieces
+}
+
+/// Returns the first layout with the highest score
+fn pick_best<S: PartialOrd>(
+    layouts: impl Iterator<Item = Vec<BOX>>,
+    score: impl Fn(&[BOX]) -> S,
+) -> Vec<BOX> {
+    let mut best: Option<(S, Vec<BOX>)> = None;
+    for layout in layouts {
+        let s = score(&layout);
+        match &best {
+            Some((best_score, _)) if s <= *best_score => {}
+            _ => best = Some((s, layout)),
+        }
+    }
+    best.map(|(_, l)| l).unwrap_or_default()
 }
 
 pub fn one_vertex_intersection(
@@ -138,10 +326,7 @@ pub fn one_vertex_intersection(
             )
             /////
         }
-        i => {
-            dbg!("How the hell", i);
-            unreachable!()
-        }
+        i => unreachable!("rotation {i} of one vertex intersection is not in 0..4"),
     }
 }
 
@@ -285,9 +470,9 @@ pub fn two_vertex_intersection_subdivision(
             )
             ////
         }
-        _ => {
-            println!("Something unpredictable happened");
-            unreachable!()
-        }
+        v => unreachable!(
+            "vertices {v:?} of {new_min:?}..{new_max:?} in {free_min:?}..{free_max:?} \
+             are not two adjacent corners"
+        ),
     }
 }
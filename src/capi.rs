@@ -0,0 +1,235 @@
+//! C ABI for non-Python consumers, header include/aabb_occlusion_culling.h is generated by cbindgen.
+//! Functions catch panics and report them as AOC_STATUS_PANIC, the buffer should be
+//! destroyed after that, since its state may be inconsistent.
+use std::os::raw::{c_char, c_int};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use box_intersect_ze::boxes::BBox;
+
+use crate::box_cutting::*;
+use crate::{OcclusionBuffer, OcclusionQuery, OcclusionStatus};
+
+pub const AOC_STRATEGY_GUILLOTINE: i32 = 0;
+pub const AOC_STRATEGY_VERTICAL: i32 = 1;
+pub const AOC_STRATEGY_HORIZONTAL: i32 = 2;
+pub const AOC_STRATEGY_MAX_RECT: i32 = 3;
+pub const AOC_STRATEGY_MIN_FRAGMENTS: i32 = 4;
+
+/// Result of every call that can fail
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AocStatus {
+    Ok = 0,
+    NullPointer = 1,
+    /// Box has NaN coordinates or is empty (lo >= hi on some axis)
+    InvalidBox = 2,
+    /// Output array is smaller than needed, required count is still written
+    BufferTooSmall = 3,
+    /// Buffer code panicked, destroy the buffer
+    Panic = 4,
+}
+
+/// Axis-aligned box, lo is the bottom left corner
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct AocBox {
+    pub lo: [f32; 2],
+    pub hi: [f32; 2],
+}
+
+/// Opaque handle to OcclusionBuffer
+pub struct AocBuffer {
+    buffer: OcclusionBuffer,
+}
+
+impl AocBox {
+    fn to_box(self) -> Result<BOX, AocStatus> {
+        match self.lo[0] < self.hi[0] && self.lo[1] < self.hi[1] {
+            true => Ok(BOX::new(self.lo, self.hi)),
+            // also catches NaN, since comparisons with it are false
+            false => Err(AocStatus::InvalidBox),
+        }
+    }
+
+    fn from_box(b: &BOX) -> Self {
+        Self {
+            lo: [b.lo(0), b.lo(1)],
+            hi: [b.hi(0), b.hi(1)],
+        }
+    }
+}
+
+fn guarded(f: impl FnOnce() -> Result<(), AocStatus>) -> AocStatus {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => AocStatus::Ok,
+        Ok(Err(status)) => status,
+        Err(_) => AocStatus::Panic,
+    }
+}
+
+/// Creates a buffer with given bounds and one of AOC_STRATEGY_* constants.
+/// Returns NULL for invalid bounds or unknown strategy.
+#[no_mangle]
+pub extern "C" fn aoc_buffer_new(bounds: AocBox, strategy: i32) -> *mut AocBuffer {
+    let strategy = match strategy {
+        AOC_STRATEGY_GUILLOTINE => SplitStrategy::Guillotine,
+        AOC_STRATEGY_VERTICAL => SplitStrategy::VerticalStrips,
+        AOC_STRATEGY_HORIZONTAL => SplitStrategy::HorizontalStrips,
+        AOC_STRATEGY_MAX_RECT => SplitStrategy::MaxLargestRect,
+        AOC_STRATEGY_MIN_FRAGMENTS => SplitStrategy::MinFragments,
+        _ => return std::ptr::null_mut(),
+    };
+    match bounds.to_box() {
+        Ok(b) => Box::into_raw(Box::new(AocBuffer {
+            buffer: OcclusionBuffer::with_strategy(b, strategy),
+        })),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// Destroys a buffer, NULL is ignored.
+///
+/// # Safety
+/// buffer must come from aoc_buffer_new and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn aoc_buffer_free(buffer: *mut AocBuffer) {
+    if !buffer.is_null() {
+        drop(Box::from_raw(buffer));
+    }
+}
+
+/// Sets *visible to whether any part of the box is free, updating query counters.
+///
+/// # Safety
+/// buffer must come from aoc_buffer_new, visible must point to writable bool.
+#[no_mangle]
+pub unsafe extern "C" fn aoc_query(
+    buffer: *mut AocBuffer,
+    b: AocBox,
+    visible: *mut bool,
+) -> AocStatus {
+    if buffer.is_null() || visible.is_null() {
+        return AocStatus::NullPointer;
+    }
+    guarded(|| {
+        let status = (*buffer).buffer.query(&b.to_box()?);
+        *visible = status == OcclusionStatus::PartiallyVisible;
+        Ok(())
+    })
+}
+
+/// Marks the box as occupied, it may touch or cross the bounds.
+///
+/// # Safety
+/// buffer must come from aoc_buffer_new.
+#[no_mangle]
+pub unsafe extern "C" fn aoc_insert(buffer: *mut AocBuffer, b: AocBox) -> AocStatus {
+    if buffer.is_null() {
+        return AocStatus::NullPointer;
+    }
+    guarded(|| {
+        (*buffer).buffer.insert(b.to_box()?);
+        Ok(())
+    })
+}
+
+/// Culls count boxes in order (front to back): each box is checked, visible ones are inserted.
+/// Visibility of boxes[i] goes to visible[i]. Stops at the first invalid box.
+///
+/// # Safety
+/// buffer must come from aoc_buffer_new, boxes and visible must point to count elements.
+#[no_mangle]
+pub unsafe extern "C" fn aoc_cull_batch(
+    buffer: *mut AocBuffer,
+    boxes: *const AocBox,
+    count: usize,
+    visible: *mut bool,
+) -> AocStatus {
+    if buffer.is_null() || (count > 0 && (boxes.is_null() || visible.is_null())) {
+        return AocStatus::NullPointer;
+    }
+    if count == 0 {
+        return AocStatus::Ok;
+    }
+    let boxes = std::slice::from_raw_parts(boxes, count);
+    let visible = std::slice::from_raw_parts_mut(visible, count);
+    guarded(|| {
+        let buffer = &mut (*buffer).buffer;
+        for (b, v) in boxes.iter().zip(visible.iter_mut()) {
+            let b = b.to_box()?;
+            *v = buffer.query(&b) == OcclusionStatus::PartiallyVisible;
+            if *v {
+                buffer.insert(b);
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Writes live free space fragments into out. *count receives the number of fragments even if
+/// capacity is too small, so calling with capacity 0 asks for the needed size.
+///
+/// # Safety
+/// buffer must come from aoc_buffer_new, out must point to capacity elements (may be NULL
+/// when capacity is 0), count must point to writable size_t.
+#[no_mangle]
+pub unsafe extern "C" fn aoc_free_space(
+    buffer: *const AocBuffer,
+    out: *mut AocBox,
+    capacity: usize,
+    count: *mut usize,
+) -> AocStatus {
+    if buffer.is_null() || count.is_null() || (capacity > 0 && out.is_null()) {
+        return AocStatus::NullPointer;
+    }
+    guarded(|| {
+        let buffer = &(*buffer).buffer;
+        *count = buffer.free_boxes().count();
+        if *count > capacity {
+            return Err(AocStatus::BufferTooSmall);
+        }
+        for (i, b) in buffer.free_boxes().enumerate() {
+            *out.add(i) = AocBox::from_box(b);
+        }
+        Ok(())
+    })
+}
+
+/// Restores full free space.
+///
+/// # Safety
+/// buffer must come from aoc_buffer_new.
+#[no_mangle]
+pub unsafe extern "C" fn aoc_reset(buffer: *mut AocBuffer) -> AocStatus {
+    if buffer.is_null() {
+        return AocStatus::NullPointer;
+    }
+    guarded(|| {
+        (*buffer).buffer.reset();
+        Ok(())
+    })
+}
+
+/// Static description of a status code. Takes a plain int, so codes that are not
+/// AocStatus values (e.g. from a newer header) get "unknown status" instead of undefined behaviour.
+#[no_mangle]
+pub extern "C" fn aoc_status_message(status: c_int) -> *const c_char {
+    let known = [
+        AocStatus::Ok,
+        AocStatus::NullPointer,
+        AocStatus::InvalidBox,
+        AocStatus::BufferTooSmall,
+        AocStatus::Panic,
+    ]
+    .into_iter()
+    .find(|s| *s as c_int == status);
+    let msg: &'static [u8] = match known {
+        Some(AocStatus::Ok) => b"ok\0",
+        Some(AocStatus::NullPointer) => b"null pointer argument\0",
+        Some(AocStatus::InvalidBox) => b"box is empty or has NaN coordinates\0",
+        Some(AocStatus::BufferTooSmall) => b"output buffer too small\0",
+        Some(AocStatus::Panic) => b"internal panic, buffer may be inconsistent\0",
+        None => b"unknown status\0",
+    };
+    msg.as_ptr().cast()
+}
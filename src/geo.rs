@@ -0,0 +1,270 @@
+//! GeoJSON and WKT exchange of free space, for inspection in GIS tools and Shapely.
+//! Every box becomes a rectangular polygon with counter-clockwise exterior ring. GeoJSON needs the serde feature.
+use box_intersect_ze::boxes::BBox;
+#[cfg(feature = "serde")]
+use serde_json::{json, Value};
+
+use crate::box_cutting::*;
+use crate::{OcclusionBuffer, OcclusionQuery};
+
+fn ring(b: &BOX) -> Vec<[f32; 2]> {
+    let (x0, y0, x1, y1) = (b.lo(0), b.lo(1), b.hi(0), b.hi(1));
+    vec![[x0, y0], [x1, y0], [x1, y1], [x0, y1], [x0, y0]]
+}
+
+/// FeatureCollection with a Polygon per free fragment (properties kind "free" and fragment id)
+/// and, if requested, per box of the occupied region (kind "occupied"). Buffer bounds go into bbox.
+#[cfg(feature = "serde")]
+pub fn free_space_geojson(buffer: &OcclusionBuffer, with_occupied: bool) -> String {
+    let feature = |b: &BOX, properties: Value| {
+        json!({
+            "type": "Feature",
+            "geometry": {"type": "Polygon", "coordinates": [ring(b)]},
+            "properties": properties,
+        })
+    };
+    let mut features: Vec<Value> = buffer
+        .free_space
+        .boxes
+        .iter()
+        .filter(|(b, _)| b.lo(0) != NOWHERE)
+        .map(|(b, id)| feature(b, json!({"kind": "free", "id": id})))
+        .collect();
+    if with_occupied {
+        features.extend(
+            buffer
+                .occupied_boxes()
+                .iter()
+                .map(|b| feature(b, json!({"kind": "occupied"}))),
+        );
+    }
+    let bounds = buffer.bounds();
+    json!({
+        "type": "FeatureCollection",
+        "bbox": [bounds.lo(0), bounds.lo(1), bounds.hi(0), bounds.hi(1)],
+        "features": features,
+    })
+    .to_string()
+}
+
+fn multipolygon_wkt(boxes: &[BOX]) -> String {
+    if boxes.is_empty() {
+        return "MULTIPOLYGON EMPTY".to_owned();
+    }
+    let polygons: Vec<String> = boxes
+        .iter()
+        .map(|b| {
+            let points: Vec<String> = ring(b)
+                .iter()
+                .map(|p| format!("{} {}", p[0], p[1]))
+                .collect();
+            format!("(({}))", points.join(", "))
+        })
+        .collect();
+    format!("MULTIPOLYGON ({})", polygons.join(", "))
+}
+
+/// Free fragments as a WKT MultiPolygon
+pub fn free_space_wkt(buffer: &OcclusionBuffer) -> String {
+    let free: Vec<BOX> = buffer.free_boxes().copied().collect();
+    multipolygon_wkt(&free)
+}
+
+/// Occupied part of the bounds as a WKT MultiPolygon
+pub fn occupied_wkt(buffer: &OcclusionBuffer) -> String {
+    multipolygon_wkt(&buffer.occupied_boxes())
+}
+
+/// Box from a ring that traces an axis-aligned rectangle, closing point is optional
+fn rect_from_ring(points: &[[f32; 2]]) -> Result<BOX, String> {
+    let mut pts = points.to_vec();
+    if pts.len() == 5 && pts[0] == pts[4] {
+        pts.pop();
+    }
+    let not_rect = || format!("Polygon {points:?} is not an axis-aligned rectangle");
+    if pts.len() != 4 {
+        return Err(not_rect());
+    }
+    for i in 0..4 {
+        let (a, b) = (pts[i], pts[(i + 1) % 4]);
+        // every edge runs along exactly one axis
+        if (a[0] == b[0]) == (a[1] == b[1]) {
+            return Err(not_rect());
+        }
+    }
+    let lo = [
+        pts.iter().map(|p| p[0]).fold(f32::MAX, f32::min),
+        pts.iter().map(|p| p[1]).fold(f32::MAX, f32::min),
+    ];
+    let hi = [
+        pts.iter().map(|p| p[0]).fold(f32::MIN, f32::max),
+        pts.iter().map(|p| p[1]).fold(f32::MIN, f32::max),
+    ];
+    Ok(BOX::new(lo, hi))
+}
+
+/// Builds a buffer whose free space is given by fragments, then marks occupied boxes as occluded.
+/// Bounds default to the box enclosing all polygons.
+fn build(
+    free: Vec<BOX>,
+    occupied: Vec<BOX>,
+    bounds: Option<BOX>,
+    strategy: SplitStrategy,
+) -> Result<OcclusionBuffer, String> {
+    for (i, a) in free.iter().enumerate() {
+        if let Some(b) = free[i + 1..].iter().find(|b| a.overlaps_with(b)) {
+            return Err(format!("Free space polygons {a:?} and {b:?} overlap"));
+        }
+    }
+    let bounds = match bounds {
+        Some(b) => b,
+        None => {
+            let all: Vec<&BOX> = free.iter().chain(&occupied).collect();
+            if all.is_empty() {
+                return Err("No polygons and no bounds given".to_owned());
+            }
+            let lo = |d| all.iter().map(|b| b.lo(d)).fold(f32::MAX, f32::min);
+            let hi = |d| all.iter().map(|b| b.hi(d)).fold(f32::MIN, f32::max);
+            BOX::new([lo(0), lo(1)], [hi(0), hi(1)])
+        }
+    };
+    if let Some(b) = free.iter().find(|b| !bounds.encloses(b)) {
+        return Err(format!(
+            "Free space polygon {b:?} is outside of bounds {bounds:?}"
+        ));
+    }
+    let mut buffer = OcclusionBuffer::from_free_space(bounds, free, strategy);
+    for b in occupied {
+        buffer.insert(b);
+    }
+    Ok(buffer)
+}
+
+#[cfg(feature = "serde")]
+fn geojson_rects(geometry: &Value) -> Result<Vec<BOX>, String> {
+    let polygon = |rings: &Value| -> Result<BOX, String> {
+        let rings = rings
+            .as_array()
+            .ok_or("Polygon coordinates should be an array of rings")?;
+        match rings.len() {
+            1 => {}
+            0 => return Err("Polygon without rings".to_owned()),
+            _ => return Err("Polygons with holes are not supported".to_owned()),
+        }
+        let points: Vec<[f32; 2]> = serde_json::from_value(rings[0].clone())
+            .map_err(|e| format!("Bad ring coordinates: {e}"))?;
+        rect_from_ring(&points)
+    };
+    let coords = &geometry["coordinates"];
+    match geometry["type"].as_str() {
+        Some("Polygon") => Ok(vec![polygon(coords)?]),
+        Some("MultiPolygon") => coords
+            .as_array()
+            .ok_or("MultiPolygon coordinates should be an array")?
+            .iter()
+            .map(polygon)
+            .collect(),
+        other => Err(format!("Unsupported geometry type {other:?}")),
+    }
+}
+
+/// Builds a buffer from a FeatureCollection, Feature or bare (Multi)Polygon made of axis-aligned rectangles.
+/// Polygons are free space, except features with property kind "occupied" which are inserted as occluders.
+/// Bounds are taken from bbox of the collection if present.
+#[cfg(feature = "serde")]
+pub fn buffer_from_geojson(text: &str, strategy: SplitStrategy) -> Result<OcclusionBuffer, String> {
+    let doc: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    let features = match doc["type"].as_str() {
+        Some("FeatureCollection") => doc["features"]
+            .as_array()
+            .ok_or("FeatureCollection without features")?
+            .clone(),
+        Some("Feature") => vec![doc.clone()],
+        _ => vec![json!({"type": "Feature", "geometry": doc.clone()})],
+    };
+    let (mut free, mut occupied) = (vec![], vec![]);
+    for f in &features {
+        let rects = geojson_rects(&f["geometry"])?;
+        match f["properties"]["kind"].as_str() {
+            Some("occupied") => occupied.extend(rects),
+            _ => free.extend(rects),
+        }
+    }
+    let bounds = match serde_json::from_value::<[f32; 4]>(doc["bbox"].clone()) {
+        Ok(b) => Some(BOX::new([b[0], b[1]], [b[2], b[3]])),
+        Err(_) => None,
+    };
+    build(free, occupied, bounds, strategy)
+}
+
+/// Parses rectangles of a WKT Polygon or MultiPolygon, holes are rejected
+fn wkt_rects(text: &str) -> Result<Vec<BOX>, String> {
+    let t = text.trim();
+    let upper = t.to_ascii_uppercase();
+    let (polygon_depth, body) = if upper.starts_with("MULTIPOLYGON") {
+        (2, &t["MULTIPOLYGON".len()..])
+    } else if upper.starts_with("POLYGON") {
+        (1, &t["POLYGON".len()..])
+    } else {
+        return Err(format!("Expected POLYGON or MULTIPOLYGON, got {t:?}"));
+    };
+    if body.trim().eq_ignore_ascii_case("EMPTY") {
+        return Ok(vec![]);
+    }
+    let mut res = vec![];
+    let mut depth = 0;
+    let mut rings_in_polygon = 0;
+    let mut ring_start = 0;
+    for (i, c) in body.char_indices() {
+        match c {
+            '(' => {
+                depth += 1;
+                if depth == polygon_depth {
+                    rings_in_polygon = 0;
+                }
+                ring_start = i + 1;
+            }
+            ')' => {
+                if depth == polygon_depth + 1 {
+                    rings_in_polygon += 1;
+                    if rings_in_polygon > 1 {
+                        return Err("Polygons with holes are not supported".to_owned());
+                    }
+                    let points = body[ring_start..i]
+                        .split(',')
+                        .map(|p| {
+                            let v: Vec<f32> = p
+                                .split_whitespace()
+                                .map(|x| x.parse::<f32>().map_err(|e| format!("{e} in {p:?}")))
+                                .collect::<Result<_, _>>()?;
+                            match v.len() {
+                                2 | 3 => Ok([v[0], v[1]]),
+                                _ => Err(format!("Bad point {p:?}")),
+                            }
+                        })
+                        .collect::<Result<Vec<_>, String>>()?;
+                    res.push(rect_from_ring(&points)?);
+                }
+                if depth == 0 {
+                    return Err("Unbalanced parentheses".to_owned());
+                }
+                depth -= 1;
+            }
+            _ => {}
+        }
+    }
+    match depth {
+        0 => Ok(res),
+        _ => Err("Unbalanced parentheses".to_owned()),
+    }
+}
+
+/// Builds a buffer whose free space is a WKT (Multi)Polygon made of axis-aligned rectangles.
+/// Bounds default to the box enclosing all polygons.
+pub fn buffer_from_wkt(
+    text: &str,
+    bounds: Option<BOX>,
+    strategy: SplitStrategy,
+) -> Result<OcclusionBuffer, String> {
+    build(wkt_rects(text)?, vec![], bounds, strategy)
+}
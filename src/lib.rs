@@ -1,62 +1,53 @@
+mod backend;
 mod box_cutting;
+#[cfg(feature = "capi")]
+mod capi;
+mod geo;
+mod maxrects;
+mod oplog;
+mod payload;
+#[cfg(feature = "plot")]
+mod plot;
+#[cfg(feature = "python")]
+mod python;
+mod raster;
+mod scene;
+mod stats;
+mod trace;
+#[cfg(feature = "debug_plots")]
 mod utils;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod proptests;
 
 use box_cutting::*;
-use box_intersect_ze::boxes::BBox;
 use box_intersect_ze::set::BBoxSet;
-use box_intersect_ze::*;
-use pyo3::*;
+#[cfg(feature = "debug_plots")]
 use utils::*;
 
-#[pyclass]
-#[derive(Clone)]
-pub struct PyOcclusionBuffer {
-    occl_buf: OcclusionBuffer,
-}
-
-#[pymethods]
-impl PyOcclusionBuffer {
-    #[new]
-    pub fn new(bot: [f32; 2], top: [f32; 2]) -> Self {
-        Self {
-            occl_buf: OcclusionBuffer::new(BOX::safe_new(bot, top)),
-        }
-    }
-
-    pub fn copy(&self) -> PyOcclusionBuffer {
-        self.clone()
-    }
-
-    /// check if a new box intersects free space
-    pub fn check_a_box(&mut self, new: ([f32; 2], [f32; 2])) -> bool {
-        match self.occl_buf.check_a_box(BOX::new(new.0, new.1)) {
-            OcclusionStatus::Occluded => false,
-            OcclusionStatus::PartiallyVisible => true,
-        }
-    }
-
-    /// Adds box that was last passed into check_a_box
-    pub fn add_last_box(&mut self) {
-        self.occl_buf.add_last_box();
-    }
-
-    /// add multiple boxes into zbuffer while cutting space for each one
-    pub fn add_box_set(&mut self, boxes: Vec<([f32; 2], [f32; 2])>) {
-        let mut box_vec = vec![];
-        for b in &boxes {
-            box_vec.push(BOX::new(b.0, b.1));
-        }
-        self.occl_buf.add_box_set(box_vec);
-    }
-}
-use pyo3::types::PyModule;
-#[pymodule]
-fn aabb_occlusion_culling(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
-    m.add_class::<PyOcclusionBuffer>()?;
-    Ok(())
-}
-
-#[derive(PartialEq)]
+pub use backend::{BackendKind, FreeSpaceBackend, OcclusionQuery};
+pub use box_cutting::{BoxExtensions, SplitStrategy, BOX};
+pub use box_intersect_ze::boxes::BBox;
+#[cfg(feature = "serde")]
+pub use geo::{buffer_from_geojson, free_space_geojson};
+pub use geo::{buffer_from_wkt, free_space_wkt, occupied_wkt};
+pub use maxrects::MaxRectsBuffer;
+pub use oplog::{Op, OpLog};
+pub use payload::PayloadBuffer;
+#[cfg(feature = "plot")]
+pub use plot::{export_png, export_svg, render_rgba, svg_string, BoxStyle, Color, PlotOptions};
+#[cfg(feature = "python")]
+pub use python::{PyBox, PyCullIter, PyOcclusionBuffer, PyScene};
+pub use raster::RasterBuffer;
+pub use scene::{Role, Scene, SceneOccluder, SceneQuery, SCENE_VERSION};
+pub use stats::{BufferStats, QueryCounters};
+pub use trace::{Trace, TraceFrame, TraceStep};
+#[cfg(feature = "wasm")]
+pub use wasm::WasmOcclusionBuffer;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum OcclusionStatus {
     Occluded,
     PartiallyVisible,
@@ -72,10 +63,28 @@ pub struct OcclusionBuffer {
     occlusion_status: Vec<(usize, usize)>,
     // boxes of free space that are not in use at the moment (i.e. holes in free_space array)
     dead_boxes: Vec<usize>,
+    split_strategy: SplitStrategy,
+    // number of add_last_box calls
+    cuts: usize,
+    counters: Option<QueryCounters>,
+    // fraction of dead slots in free_space above which it gets compacted after insertion
+    compaction_threshold: Option<f32>,
+    // recording of queries and insertions, see start_trace
+    trace: Option<Trace>,
+    // operations since start_recording, for replaying crashed sessions
+    log: Option<OpLog>,
 }
 
+/// Default fraction of dead slots in free_space that triggers compaction
+pub const DEFAULT_COMPACTION_THRESHOLD: f32 = 0.25;
+
 impl OcclusionBuffer {
     pub fn new(freespace: BOX) -> Self {
+        Self::with_strategy(freespace, SplitStrategy::default())
+    }
+
+    /// Creates a buffer that breaks up free space according to given strategy
+    pub fn with_strategy(freespace: BOX, split_strategy: SplitStrategy) -> Self {
         OcclusionBuffer {
             free_space: {
                 let mut s = BBoxSet::with_capacity(256);
@@ -87,15 +96,97 @@ impl OcclusionBuffer {
             box_idx_alloc: 1..,
             occlusion_status: Vec::with_capacity(128),
             dead_boxes: vec![],
+            split_strategy,
+            cuts: 0,
+            counters: None,
+            compaction_threshold: Some(DEFAULT_COMPACTION_THRESHOLD),
+            trace: None,
+            log: None,
         }
     }
 
+    /// Creates a buffer with given fragments of free space instead of the whole bounds.
+    /// Fragments must lie within bounds and not overlap each other, checked in debug builds.
+    pub fn from_free_space(
+        freespace: BOX,
+        fragments: Vec<BOX>,
+        split_strategy: SplitStrategy,
+    ) -> Self {
+        debug_assert!(
+            fragments.iter().all(|b| freespace.encloses(b)),
+            "Free space fragments should lie within bounds"
+        );
+        debug_assert!(
+            fragments
+                .iter()
+                .enumerate()
+                .all(|(i, a)| fragments[i + 1..].iter().all(|b| !a.overlaps_with(b))),
+            "Free space fragments should not overlap"
+        );
+        let mut res = Self::with_strategy(freespace, split_strategy);
+        res.free_space.boxes.clear();
+        for b in fragments {
+            res.free_space.push(res.box_idx_alloc.next().unwrap(), b);
+        }
+        res.free_space.sort();
+        res
+    }
+
+    pub fn split_strategy(&self) -> SplitStrategy {
+        self.split_strategy
+    }
+
+    pub fn bounds(&self) -> BOX {
+        self.overall_bound_box
+    }
+
+    /// Part of the bounds not covered by free space, as non-overlapping boxes
+    pub fn occupied_boxes(&self) -> Vec<BOX> {
+        let free: Vec<BOX> = self.free_boxes().copied().collect();
+        complement(&self.overall_bound_box, &free)
+    }
+
+    /// Iterates over fragments of free space, skipping deleted ones
+    pub fn free_boxes(&self) -> impl Iterator<Item = &BOX> {
+        self.free_space
+            .boxes
+            .iter()
+            .map(|(b, _)| b)
+            .filter(|b| b.lo(0) != NOWHERE)
+    }
+
     /// check if a new box intersects free space
     pub fn check_a_box(&mut self, new: BOX) -> OcclusionStatus {
+        self.record(Op::Check(new));
         assert!(
             self.overall_bound_box.contains(new),
             "New box should not be out of bounds of free space"
         );
+        self.run_query(new)
+    }
+
+    /// Query path shared by check_a_box and OcclusionQuery::query: intersects the box
+    /// with free space, then updates counters and trace
+    pub(crate) fn run_query(&mut self, new: BOX) -> OcclusionStatus {
+        let status = self.find_intersections(new);
+        if let Some(c) = &mut self.counters {
+            c.queries += 1;
+            match status {
+                OcclusionStatus::PartiallyVisible => c.visible += 1,
+                OcclusionStatus::Occluded => c.occluded += 1,
+            }
+        }
+        if let Some(t) = &mut self.trace {
+            t.frames.push(TraceFrame {
+                step: TraceStep::Query { query: new, status },
+                free_space: live_fragments(&self.free_space),
+            });
+        }
+        status
+    }
+
+    /// Same as check_a_box, but allows new box to touch or cross the bounds
+    pub(crate) fn find_intersections(&mut self, new: BOX) -> OcclusionStatus {
         self.new_box.clear();
         self.occlusion_status.clear();
         self.new_box.push(usize::MAX - 1, new);
@@ -109,41 +200,254 @@ impl OcclusionBuffer {
         }
     }
 
+    /// Box passed into the last check_a_box, until it is added
+    #[cfg(feature = "python")]
+    pub(crate) fn pending_box(&self) -> Option<BOX> {
+        self.new_box.boxes.first().map(|(b, _)| *b)
+    }
+
     /// Adds box that was last passed into check_a_box
     pub fn add_last_box(&mut self) {
+        self.record(Op::AddLastBox);
         //take stuff from self.new_box
         assert!(!self.occlusion_status.is_empty());
         assert!(!self.new_box.empty());
 
         let newbox = self.new_box.boxes[0].0;
-        println!("Cutting space for new box: {:?}", newbox);
+        #[cfg(feature = "debug_plots")]
+        {
+            println!("Cutting space for new box: {:?}", newbox);
+            plotboxes(&self.free_space, &self.new_box, "add_last_box_start");
+            use std::process::Command;
+            let _ = Command::new("/bin/sh")
+                .arg("-c")
+                .arg("rm -f cutspace_iteration*svg")
+                .status();
+        }
+        let traced = self.trace.as_ref().map(|_| {
+            let intersected: Vec<_> = self
+                .occlusion_status
+                .iter()
+                .map(|&(i, _)| self.free_space.boxes[i])
+                .collect();
+            (intersected, self.box_idx_alloc.start)
+        });
         // break up free space to accommodate new box
-        plotboxes(&self.free_space, &self.new_box, "add_last_box_start");
-        use std::process::Command;
-        let _ = Command::new("/bin/sh")
-            .arg("-c")
-            .arg("rm -f cutspace_iteration*svg")
-            .status();
         cut_space(
             &mut self.free_space,
             &mut self.dead_boxes,
             &self.occlusion_status,
             &newbox,
             &mut self.box_idx_alloc,
+            self.split_strategy,
         );
+        self.cuts += 1;
+        self.new_box.clear();
+        if let Some(threshold) = self.compaction_threshold {
+            if self.dead_boxes.len() as f32 > threshold * self.free_space.boxes.len() as f32 {
+                self.remove_dead_slots();
+            }
+        }
+        if let (Some(t), Some((intersected, first_new))) = (&mut self.trace, traced) {
+            let free_space = live_fragments(&self.free_space);
+            t.frames.push(TraceFrame {
+                step: TraceStep::Insert {
+                    new: newbox,
+                    intersected,
+                    produced: free_space
+                        .iter()
+                        .filter(|(_, i)| *i >= first_new)
+                        .copied()
+                        .collect(),
+                },
+                free_space,
+            });
+        }
+    }
+
+    /// Physically removes deleted fragments from free_space, keeping it sorted.
+    /// Positions of fragments change, but their indices stay the same,
+    /// so indices remain valid handles to fragments.
+    pub fn compact(&mut self) {
+        self.record(Op::Compact);
+        self.remove_dead_slots();
+    }
+
+    fn remove_dead_slots(&mut self) {
+        if self.dead_boxes.is_empty() {
+            return;
+        }
+        let mut new_pos = vec![usize::MAX; self.free_space.boxes.len()];
+        let mut kept = 0;
+        for (i, (b, _)) in self.free_space.boxes.iter().enumerate() {
+            if b.lo(0) != NOWHERE {
+                new_pos[i] = kept;
+                kept += 1;
+            }
+        }
+        self.free_space.boxes.retain(|(b, _)| b.lo(0) != NOWHERE);
+        // keep result of pending check_a_box valid
+        for (i, _) in self.occlusion_status.iter_mut() {
+            *i = new_pos[*i];
+        }
+        self.dead_boxes.clear();
+    }
+
+    /// Restores full free space (e.g. for a new frame), keeping allocated memory and settings.
+    /// Query counters keep counting, use enable_counters to restart them.
+    pub fn reset(&mut self) {
+        self.record(Op::Reset);
+        self.free_space.boxes.clear();
+        self.free_space.push(0, self.overall_bound_box);
         self.new_box.clear();
+        self.occlusion_status.clear();
+        self.dead_boxes.clear();
+        self.box_idx_alloc = 1..;
+        self.cuts = 0;
+    }
+
+    /// Changes bounds while keeping occluders in place (e.g. on window resize).
+    /// Growing adds free space at the margins, shrinking clips fragments to new bounds.
+    /// Result of a pending check_a_box is discarded.
+    pub fn set_bounds(&mut self, bounds: BOX) {
+        self.record(Op::SetBounds(bounds));
+        self.clip_to_bounds(bounds);
+    }
+
+    fn clip_to_bounds(&mut self, bounds: BOX) {
+        for (b, _) in self.free_space.boxes.iter_mut() {
+            if b.lo(0) == NOWHERE {
+                continue;
+            }
+            let lo = [b.lo(0).max(bounds.lo(0)), b.lo(1).max(bounds.lo(1))];
+            let hi = [b.hi(0).min(bounds.hi(0)), b.hi(1).min(bounds.hi(1))];
+            *b = match lo[0] < hi[0] && lo[1] < hi[1] {
+                true => BOX::new(lo, hi),
+                false => BOX::new([NOWHERE, NOWHERE], [NOWHERE, NOWHERE]),
+            };
+        }
+        // parts of new bounds outside of old ones have never been occluded
+        for margin in split_remainder(
+            &bounds,
+            &self.overall_bound_box,
+            SplitStrategy::VerticalStrips,
+        ) {
+            self.free_space
+                .push(self.box_idx_alloc.next().unwrap(), margin);
+        }
+        self.overall_bound_box = bounds;
+        self.new_box.clear();
+        self.occlusion_status.clear();
+        sort_free_space(&mut self.free_space, &mut self.dead_boxes);
+    }
+
+    /// Moves occluded area by offset (panning), bounds stay in place.
+    /// Fragments leaving the bounds are clipped, exposed edges become free space.
+    pub fn translate(&mut self, offset: [f32; 2]) {
+        self.record(Op::Translate(offset));
+        self.transform(|p, d| p + offset[d]);
+    }
+
+    /// Scales occluded area around center by positive factors per axis (zoom), bounds stay in place.
+    pub fn scale(&mut self, center: [f32; 2], factors: [f32; 2]) {
+        assert!(
+            factors[0] > 0.0 && factors[1] > 0.0,
+            "Scale factors must be positive, got {factors:?}"
+        );
+        self.record(Op::Scale { center, factors });
+        self.transform(|p, d| center[d] + (p - center[d]) * factors[d]);
+    }
+
+    /// Applies monotonic per-axis transform to all fragments, then clips back to old bounds.
+    /// Equal coordinates stay equal, so fragments remain disjoint.
+    fn transform(&mut self, f: impl Fn(f32, usize) -> f32) {
+        let bounds = self.overall_bound_box;
+        let tr = |b: &BOX| {
+            BOX::new(
+                [f(b.lo(0), 0), f(b.lo(1), 1)],
+                [f(b.hi(0), 0), f(b.hi(1), 1)],
+            )
+        };
+        for (b, _) in self.free_space.boxes.iter_mut() {
+            if b.lo(0) != NOWHERE {
+                *b = tr(b);
+            }
+        }
+        self.overall_bound_box = tr(&bounds);
+        self.clip_to_bounds(bounds);
+    }
+
+    /// Starts recording every check_a_box and add_last_box call, dropping previous recording
+    pub fn start_trace(&mut self) {
+        self.trace = Some(Trace::new(self.overall_bound_box));
+    }
+
+    /// Stops recording and returns the trace, if one was started
+    pub fn take_trace(&mut self) -> Option<Trace> {
+        self.trace.take()
+    }
+
+    /// Sets fraction of dead slots that triggers compaction after insertion, None disables it
+    pub fn set_compaction_threshold(&mut self, threshold: Option<f32>) {
+        self.record(Op::SetCompactionThreshold(threshold));
+        self.compaction_threshold = threshold;
+    }
+
+    /// Starts logging operations that change the buffer, dropping previous log.
+    /// Log begins with current bounds, settings and free space, see OpLog::replay.
+    pub fn start_recording(&mut self) {
+        self.log = Some(OpLog::of_buffer(self));
+    }
+
+    /// Operations recorded so far. Operations are logged before they run,
+    /// so after a panic the log ends with the operation that caused it.
+    pub fn recording(&self) -> Option<&OpLog> {
+        self.log.as_ref()
+    }
+
+    /// Stops recording and returns the log
+    pub fn take_recording(&mut self) -> Option<OpLog> {
+        self.log.take()
+    }
+
+    fn record(&mut self, op: Op) {
+        if let Some(log) = &mut self.log {
+            log.ops.push(op);
+        }
+    }
+
+    /// Enables (and resets) or disables counting of check_a_box calls
+    pub fn enable_counters(&mut self, enable: bool) {
+        self.counters = enable.then(QueryCounters::default);
+    }
+
+    /// Collects statistics about fragmentation of free space
+    pub fn stats(&self) -> BufferStats {
+        BufferStats {
+            fragments: self.free_boxes().count(),
+            dead_slots: self.dead_boxes.len(),
+            free_area: self.free_boxes().map(|b| b.area()).sum(),
+            largest_fragment: self.free_boxes().map(|b| b.area()).fold(0.0, f32::max),
+            cuts: self.cuts,
+            next_index: self.box_idx_alloc.start,
+            counters: self.counters,
+        }
     }
 
     /// add multiple boxes into zbuffer while cutting space for each one
     pub fn add_box_set(&mut self, boxes: Vec<BOX>) {
         for b in boxes {
-            match self.check_a_box(b) {
-                OcclusionStatus::Occluded => {
-                    continue;
-                }
-                OcclusionStatus::PartiallyVisible => self.add_last_box(),
-            }
+            self.cull_box(b);
+        }
+    }
+
+    /// Checks a box and adds it if it is partially visible, returning the check result
+    pub fn cull_box(&mut self, b: BOX) -> OcclusionStatus {
+        let status = self.check_a_box(b);
+        if status == OcclusionStatus::PartiallyVisible {
+            self.add_last_box();
         }
+        status
     }
 }
 
@@ -155,6 +459,7 @@ fn cut_space(
     intersected: &[(usize, usize)],
     new: &BOX,
     start_idx: &mut std::ops::RangeFrom<usize>,
+    strategy: SplitStrategy,
 ) {
     let new_min = (new.lo(0), new.lo(1));
     let new_max = (new.hi(0), new.hi(1));
@@ -173,6 +478,7 @@ fn cut_space(
 
         let new_in_free_count = new_verts_in_free.iter().map(|&e| e as u8).sum();
         let free_in_new_count = free_verts_in_new.iter().map(|&e| e as u8).sum();
+        let free_box = *free;
         // Delete the (now invalid) free box by moving it into "Nowhere"
         *free = BOX::new([NOWHERE, NOWHERE], [NOWHERE, NOWHERE]);
         // Add it to freelist for memory reuse
@@ -193,6 +499,12 @@ fn cut_space(
         };
 
         match (new_in_free_count, free_in_new_count) {
+            // layouts other than guillotine do not depend on the intersection case
+            _ if strategy != SplitStrategy::Guillotine => {
+                for b in split_remainder(&free_box, new, strategy) {
+                    maybe_push2(b);
+                }
+            }
             // new box completely covers free, free should be removed (which it already is)
             (0, 4) => {}
             //free entirely contains new, break free into 4 segments
@@ -293,71 +605,88 @@ fn cut_space(
                 let has_bot = new_min.1 > free_min.1;
 
                 if (has_bot || has_top) == (has_left || has_right) {
-                    dbg!(new_min.0, new_min.1);
-                    dbg!(new_max.0, new_max.1);
-                    dbg!(free_min.0, free_min.1);
-                    dbg!(free_max.0, free_max.1);
-                    let mut new_space = set::BBoxSet::<BOX, usize>::new();
-                    new_space.push(i, *new);
-                    plotboxes(&free_space, &new_space, "fail_test_draw");
-
-                    unreachable!("You have to visit a doctor");
-                }
-
-                if has_bot {
-                    maybe_push2(BOX::new(
-                        [free_min.0 + EPS, free_min.1 + EPS],
-                        [free_max.0 - EPS, new_min.1 - EPS],
-                    ));
-                }
-                if has_top {
-                    maybe_push2(BOX::new(
-                        [free_min.0 + EPS, new_max.1 + EPS],
-                        [free_max.0 - EPS, free_max.1 - EPS],
-                    ));
-                }
-                if has_left {
-                    maybe_push2(BOX::new(
-                        [free_min.0 + EPS, free_min.1 + EPS],
-                        [new_min.0 - EPS, free_max.1 - EPS],
-                    ));
-                }
-                if has_right {
-                    maybe_push2(BOX::new(
-                        [new_max.0 + EPS, free_min.1 + EPS],
-                        [free_max.0 - EPS, free_max.1 - EPS],
-                    ));
+                    // edges of new coincide with edges of free (e.g. new covers a corner of free
+                    // flush with its sides), so the cross pattern does not apply
+                    for b in split_remainder(&free_box, new, SplitStrategy::VerticalStrips) {
+                        maybe_push2(b);
+                    }
+                } else {
+                    if has_bot {
+                        maybe_push2(BOX::new(
+                            [free_min.0 + EPS, free_min.1 + EPS],
+                            [free_max.0 - EPS, new_min.1 - EPS],
+                        ));
+                    }
+                    if has_top {
+                        maybe_push2(BOX::new(
+                            [free_min.0 + EPS, new_max.1 + EPS],
+                            [free_max.0 - EPS, free_max.1 - EPS],
+                        ));
+                    }
+                    if has_left {
+                        maybe_push2(BOX::new(
+                            [free_min.0 + EPS, free_min.1 + EPS],
+                            [new_min.0 - EPS, free_max.1 - EPS],
+                        ));
+                    }
+                    if has_right {
+                        maybe_push2(BOX::new(
+                            [new_max.0 + EPS, free_min.1 + EPS],
+                            [free_max.0 - EPS, free_max.1 - EPS],
+                        ));
+                    }
                 }
             }
             // all other cases should never happen
-            (a, b) => {
-                println!("{a} {b} HAPPENED ");
-                dbg!(new);
-                dbg!(free_min, free_max);
-
-                let mut new_space = set::BBoxSet::<BOX, usize>::new();
-                new_space.push(i, *new);
-                plotboxes(&free_space, &new_space, "fail_test_draw");
-                unreachable!()
-            }
+            (a, b) => unreachable!(
+                "unexpected intersection ({a}, {b}) of {new:?} with {free_min:?}..{free_max:?}"
+            ),
+        }
+        #[cfg(feature = "debug_plots")]
+        {
+            let mut new_space = BBoxSet::<BOX, usize>::new();
+            new_space.push(0, *new);
+            plotboxes(free_space, &new_space, &format!("cutspace_iteration_{i}"));
         }
-        let mut new_space = set::BBoxSet::<BOX, usize>::new();
-        new_space.push(0, *new);
-        plotboxes(&free_space, &new_space, &format!("cutspace_iteration_{i}"));
     }
 
+    sort_free_space(free_space, to_overwrite);
+}
+
+/// Fragments of free space with their indices, skipping deleted ones
+fn live_fragments(free_space: &BBoxSet<BOX, usize>) -> Vec<(BOX, usize)> {
+    free_space
+        .boxes
+        .iter()
+        .filter(|(b, _)| b.lo(0) != NOWHERE)
+        .copied()
+        .collect()
+}
+
+/// Sorts free space and collects slots of deleted boxes again, since sorting moves them around
+fn sort_free_space(free_space: &mut BBoxSet<BOX, usize>, dead: &mut Vec<usize>) {
     free_space.sort();
+    dead.clear();
+    dead.extend(
+        free_space
+            .boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, (b, _))| b.lo(0) == NOWHERE)
+            .map(|(i, _)| i),
+    );
 }
 
 #[cfg(test)]
 mod tests {
     use crate::*;
+    use box_intersect_ze::*;
     use stdext::function_name;
 
     fn ensure_no_self_intersections(free: &set::BBoxSet<BOX, usize>) {
         let mut res = vec![];
-        intersect_scan(&free, &free, &mut res);
-        if res.len() > 0 {
+        intersect_scan(free, free, &mut res);
+        if !res.is_empty() {
             dbg!(res);
             panic!("free space should not have self-intersections");
         }
@@ -385,7 +714,8 @@ mod tests {
         expect_num_inters: usize,
     ) {
         assert_ne!(directory.len(), 0, "Directory should not be empty!");
-        let name = directory.to_owned() + "/" + &better_name(&name);
+        let name = directory.to_owned() + "/" + &better_name(name);
+        println!("Running {name}");
         // create the struct under test
         let mut ob = OcclusionBuffer::new(BOX::new([-10.0, -10.0], [10.0, 10.0]));
 
@@ -404,7 +734,11 @@ mod tests {
         }
         new_space.sort();
 
-        plotboxes(&ob.free_space, &new_space, &(name.clone() + "__before.svg"));
+        #[cfg(feature = "debug_plots")]
+        {
+            std::fs::create_dir_all(directory).unwrap();
+            plotboxes(&ob.free_space, &new_space, &(name.clone() + "__before.svg"));
+        }
         let mut num_inters = 0;
 
         for (i, &newbox) in new.iter().enumerate() {
@@ -420,6 +754,7 @@ mod tests {
             ob.add_last_box();
 
             println!("free space after {:?}", ob.free_space.boxes);
+            #[cfg(feature = "debug_plots")]
             plotboxes(&ob.free_space, &new_space, &format!("{name}_after{i}.svg"));
 
             ensure_no_self_intersections(&ob.free_space);
@@ -454,7 +789,7 @@ mod tests {
             BOX::new([0.6, 0.3], [0.9, 0.9]),
         ]; //inside the empty
 
-        test_inner_multiple(&free, &new, ".", &function_name!(), 2);
+        test_inner_multiple(&free, &new, ".", function_name!(), 2);
     }
 
     ///Two boxes that touch in their very corner
@@ -470,6 +805,41 @@ mod tests {
         test_inner_multiple(&free, &new, ".", function_name!(), 2);
     }
 
+    fn buffer_free_area(buf: &OcclusionBuffer) -> f32 {
+        buf.free_boxes().map(|b| b.area()).sum()
+    }
+
+    /// Occluder exactly matching a fragment used to hit unreachable!() in cut_space
+    #[test]
+    pub fn cut_space_new_box_flush_with_fragment_edges() {
+        let mut buf = OcclusionBuffer::new(BOX::new([0., 0.], [4., 4.]));
+        OcclusionQuery::insert(&mut buf, BOX::new([1., 1.], [3., 3.]));
+        // the fragment above the first box, all edges coincide
+        OcclusionQuery::insert(&mut buf, BOX::new([1., 3.], [3., 4.]));
+        ensure_no_self_intersections(&buf.free_space);
+        assert_eq!(buffer_free_area(&buf), 10.0);
+    }
+
+    /// Slots of deleted fragments used to go stale when sorting moved them,
+    /// so the next cut overwrote a live fragment
+    #[test]
+    pub fn cut_space_dead_slots_point_at_deleted_fragments() {
+        let mut buf = OcclusionBuffer::new(BOX::new([0., 0.], [4., 4.]));
+        for (b, area) in [
+            (BOX::new([1., 1.], [3., 3.]), 12.0),
+            // swallows the left fragment, which leaves one slot unused
+            (BOX::new([-0.5, -0.5], [1.5, 4.5]), 7.0),
+            (BOX::new([3.5, -0.5], [4.5, 0.5]), 6.75),
+        ] {
+            OcclusionQuery::insert(&mut buf, b);
+            for &i in buf.dead_boxes.iter() {
+                assert_eq!(buf.free_space.boxes[i].0.lo(0), NOWHERE);
+            }
+            ensure_no_self_intersections(&buf.free_space);
+            assert_eq!(buffer_free_area(&buf), area);
+        }
+    }
+
     #[test]
     pub fn test_full_overlap() {
         let free = BOX::new([0., 0.], [1., 1.]); // base
@@ -486,7 +856,7 @@ mod tests {
 
     #[test]
     pub fn test_corner_overlap() {
-        let cases = vec![
+        let cases = [
             BOX::new([0.7, -0.3], [1.3, 0.3]),
             BOX::new([0.7, 0.7], [1.3, 1.3]),
             BOX::new([-0.3, 0.7], [0.3, 1.3]),
@@ -507,7 +877,7 @@ mod tests {
 
     #[test]
     pub fn test_2vertex_overlap() {
-        let cases = vec![
+        let cases = [
             BOX::new([-0.2, 0.2], [0.7, 0.7]),
             BOX::new([0.4, 0.7], [0.8, 1.3]),
             BOX::new([0.7, 0.3], [1.3, 0.7]),
@@ -527,7 +897,7 @@ mod tests {
 
     #[test]
     pub fn test_no_vertex_overlap() {
-        let cases = vec![
+        let cases = [
             BOX::new([-0.3, 0.2], [1.3, 0.7]),
             BOX::new([0.3, -0.2], [0.6, 1.3]),
         ];
@@ -546,7 +916,7 @@ mod tests {
 
     #[test]
     pub fn height_width_overlap() {
-        let cases = vec![
+        let cases = [
             BOX::new([0.7, -0.3], [1.3, 1.3]),
             BOX::new([-0.2, 0.7], [1.3, 1.3]),
             BOX::new([-0.2, -0.2], [0.2, 1.3]),
@@ -572,13 +942,771 @@ mod tests {
         test_inner(free, new, "free_in_new", function_name!(), 1);
     }
 
+    fn free_area(free: &set::BBoxSet<BOX, usize>) -> f32 {
+        free.boxes.iter().map(|(b, _)| b.area()).sum()
+    }
+
+    #[test]
+    pub fn split_strategies_equal_area() {
+        let cases = vec![
+            BOX::new([0.2, 0.2], [0.4, 0.4]),
+            BOX::new([0.4, 0.4], [0.6, 0.6]),
+            BOX::new([0.6, 0.2], [0.8, 0.4]),
+            BOX::new([0.4, 0.0], [0.6, 0.2]),
+            BOX::new([0.25, 0.1], [0.5, 0.3]),
+        ];
+        // union of cases covers 0.185
+        let expected_area = 9.0 - 0.185;
+
+        for strategy in SplitStrategy::ALL {
+            let mut ob =
+                OcclusionBuffer::with_strategy(BOX::new([-1.0, -1.0], [2.0, 2.0]), strategy);
+            for &newbox in &cases {
+                if ob.check_a_box(newbox) == OcclusionStatus::Occluded {
+                    continue;
+                }
+                ob.add_last_box();
+                ensure_no_self_intersections(&ob.free_space);
+                ensure_no_intersections(&ob.free_space, newbox);
+            }
+            let area = free_area(&ob.free_space);
+            assert!(
+                (area - expected_area).abs() < 1e-4,
+                "{strategy:?} left {area} of free area, expected {expected_area}"
+            );
+        }
+    }
+
+    #[test]
+    pub fn maxrects_find_fit() {
+        let bounds = BOX::new([0.0, 0.0], [4.0, 4.0]);
+        let occluders = [
+            BOX::new([0.0, 0.0], [1.0, 2.0]),
+            BOX::new([3.0, 2.0], [4.0, 4.0]),
+        ];
+        let mut disjoint = OcclusionBuffer::new(bounds);
+        let mut maxrects = MaxRectsBuffer::new(bounds);
+        for &b in &occluders {
+            OcclusionQuery::insert(&mut disjoint, b);
+            maxrects.occlude(b);
+        }
+        // free space is split such that 3x2 box does not fit any single disjoint fragment
+        assert!(FreeSpaceBackend::find_fit(&disjoint, 3.0, 2.0).is_none());
+        let converted = MaxRectsBuffer::from(&disjoint);
+        for backend in [&maxrects, &converted] {
+            let spot = backend.find_fit(3.0, 2.0).expect("3x2 box should fit");
+            assert!(bounds.encloses(&spot));
+            assert!(occluders.iter().all(|o| !o.overlaps_with(&spot)));
+        }
+        assert!(maxrects.find_fit(4.0, 2.5).is_none());
+    }
+
+    #[test]
+    pub fn backend_conversion_keeps_area() {
+        let bounds = BOX::new([-1.0, -1.0], [2.0, 2.0]);
+        let mut maxrects = MaxRectsBuffer::new(bounds);
+        for b in [
+            BOX::new([0.2, 0.2], [0.4, 0.4]),
+            BOX::new([0.4, 0.4], [0.6, 0.6]),
+            BOX::new([0.25, 0.1], [0.5, 0.3]),
+        ] {
+            maxrects.occlude(b);
+        }
+        let ob = OcclusionBuffer::from(&maxrects);
+        ensure_no_self_intersections(&ob.free_space);
+        assert!((free_area(&ob.free_space) - (9.0 - 0.115)).abs() < 1e-4);
+
+        let back = MaxRectsBuffer::from(&ob);
+        assert_eq!(back.free_rects().len(), maxrects.free_rects().len());
+    }
+
+    /// Runs boxes through any backend, inserting the visible ones
+    fn visibility(backend: &mut dyn OcclusionQuery, boxes: &[BOX]) -> Vec<OcclusionStatus> {
+        boxes
+            .iter()
+            .map(|b| {
+                let status = backend.query(b);
+                if status == OcclusionStatus::PartiallyVisible {
+                    backend.insert(*b);
+                }
+                status
+            })
+            .collect()
+    }
+
+    #[test]
+    pub fn raster_matches_exact_buffer() {
+        let bounds = BOX::new([0.0, 0.0], [1.0, 1.0]);
+        let boxes = [
+            BOX::new([0.2, 0.2], [0.4, 0.4]),
+            BOX::new([0.4, 0.4], [0.6, 0.6]),
+            BOX::new([0.21, 0.21], [0.39, 0.39]), // hidden behind the first one
+            BOX::new([0.4, 0.0], [0.6, 0.2]),
+            BOX::new([0.25, 0.1], [0.5, 0.3]),
+        ];
+        let mut exact = OcclusionBuffer::new(bounds);
+        let mut raster = RasterBuffer::new(bounds, 100, 100);
+        let expected = visibility(&mut exact, &boxes);
+        assert_eq!(expected[2], OcclusionStatus::Occluded);
+        assert_eq!(expected, visibility(&mut raster, &boxes));
+
+        for backend in [&mut exact as &mut dyn OcclusionQuery, &mut raster] {
+            backend.reset();
+            assert_eq!(backend.query(&bounds), OcclusionStatus::PartiallyVisible);
+            let free_area: f32 = backend.free_space_iter().map(|b| b.area()).sum();
+            assert!((free_area - 1.0).abs() < 1e-4);
+        }
+    }
+
+    /// OcclusionQuery::query runs the same intersection, counters and recording as check_a_box
+    #[test]
+    pub fn trait_query_uses_check_path() {
+        let mut ob = OcclusionBuffer::new(BOX::new([0.0, 0.0], [1.0, 1.0]));
+        ob.enable_counters(true);
+        ob.start_recording();
+        ob.add_box_set(vec![BOX::new([0.2, 0.2], [0.6, 0.6])]);
+        for b in [
+            BOX::new([0.3, 0.3], [0.4, 0.4]),
+            BOX::new([0.5, 0.5], [0.7, 0.7]),
+        ] {
+            let via_trait = OcclusionQuery::query(&mut ob, &b);
+            assert_eq!(via_trait, ob.check_a_box(b));
+        }
+        // unlike check_a_box, the trait accepts boxes touching the bounds
+        let bounds = ob.bounds();
+        assert_eq!(
+            OcclusionQuery::query(&mut ob, &bounds),
+            OcclusionStatus::PartiallyVisible
+        );
+        let counters = ob.stats().counters.unwrap();
+        assert_eq!(
+            (counters.queries, counters.visible, counters.occluded),
+            (6, 4, 2)
+        );
+
+        let log = ob.recording().unwrap().to_string();
+        assert_eq!(log.matches("\nquery ").count(), 3);
+        assert_eq!(fragments(&replay_log(&log)), fragments(&ob));
+    }
+
+    #[test]
+    pub fn buffer_stats() {
+        let mut ob = OcclusionBuffer::new(BOX::new([0.0, 0.0], [1.0, 1.0]));
+        ob.enable_counters(true);
+        ob.add_box_set(vec![
+            BOX::new([0.2, 0.2], [0.7, 0.7]),
+            BOX::new([0.3, 0.3], [0.6, 0.6]), // occluded by the first one
+        ]);
+        let stats = ob.stats();
+        assert_eq!(stats.fragments, 4);
+        assert_eq!(stats.dead_slots, 0);
+        assert_eq!(stats.cuts, 1);
+        assert!((stats.free_area - 0.75).abs() < 1e-6);
+        assert!((stats.largest_fragment - 0.3).abs() < 1e-6);
+        assert_eq!(stats.next_index, 5);
+        assert_eq!(
+            stats.counters,
+            Some(QueryCounters {
+                queries: 2,
+                visible: 1,
+                occluded: 1
+            })
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "should not overlap")]
+    pub fn from_free_space_rejects_overlapping_fragments() {
+        let free = vec![
+            BOX::new([0.0, 0.0], [2.0, 1.0]),
+            BOX::new([1.0, 0.0], [3.0, 1.0]),
+        ];
+        OcclusionBuffer::from_free_space(
+            BOX::new([-10.0, -10.0], [10.0, 10.0]),
+            free,
+            SplitStrategy::default(),
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "within bounds")]
+    pub fn from_free_space_rejects_fragments_outside_bounds() {
+        let free = vec![BOX::new([0.0, 0.0], [2.0, 1.0])];
+        OcclusionBuffer::from_free_space(
+            BOX::new([0.0, 0.0], [1.0, 1.0]),
+            free,
+            SplitStrategy::default(),
+        );
+    }
+
+    #[test]
+    pub fn compaction() {
+        let bounds = BOX::new([-10.0, -10.0], [10.0, 10.0]);
+        let free = vec![
+            BOX::new([0.0, 0.0], [1.0, 1.0]),
+            BOX::new([1.0, 0.0], [2.0, 1.0]),
+            BOX::new([2.0, 0.0], [3.0, 1.0]),
+        ];
+        // covers first two fragments completely, touches the third one
+        let newbox = BOX::new([-1.0, -1.0], [2.0, 2.0]);
+
+        let mut ob =
+            OcclusionBuffer::from_free_space(bounds, free.clone(), SplitStrategy::default());
+        ob.set_compaction_threshold(None);
+        ob.add_box_set(vec![newbox]);
+        assert_eq!(ob.stats().dead_slots, 2);
+        assert_eq!(ob.free_space.boxes.len(), 3);
+        let survivor = ob.free_space.boxes[0];
+
+        ob.compact();
+        assert_eq!(ob.stats().dead_slots, 0);
+        assert_eq!(ob.free_space.boxes.len(), 1);
+        // index of the surviving fragment is preserved
+        assert_eq!(ob.free_space.boxes[0].1, survivor.1);
+        assert_eq!(ob.free_space.boxes[0].1, 3);
+
+        let mut auto = OcclusionBuffer::from_free_space(bounds, free, SplitStrategy::default());
+        auto.set_compaction_threshold(Some(0.5));
+        auto.add_box_set(vec![newbox]);
+        assert_eq!(auto.free_space.boxes.len(), 1);
+        assert_eq!(auto.stats().dead_slots, 0);
+    }
+
+    #[test]
+    pub fn reset_keeps_allocation() {
+        let bounds = BOX::new([0.0, 0.0], [1.0, 1.0]);
+        let mut ob = OcclusionBuffer::new(bounds);
+        ob.add_box_set(vec![
+            BOX::new([0.2, 0.2], [0.4, 0.4]),
+            BOX::new([0.5, 0.5], [0.7, 0.7]),
+        ]);
+        let capacity = ob.free_space.boxes.capacity();
+        ob.reset();
+        assert_eq!(ob.free_space.boxes.capacity(), capacity);
+        assert_eq!(ob.stats().fragments, 1);
+        assert_eq!(free_area(&ob.free_space), 1.0);
+        assert_eq!(
+            ob.check_a_box(BOX::new([0.25, 0.25], [0.35, 0.35])),
+            OcclusionStatus::PartiallyVisible
+        );
+    }
+
+    #[test]
+    pub fn set_bounds_grow_and_shrink() {
+        let occluder = BOX::new([0.2, 0.2], [0.7, 0.7]);
+        let mut ob = OcclusionBuffer::new(BOX::new([0.0, 0.0], [1.0, 1.0]));
+        ob.add_box_set(vec![occluder]);
+
+        ob.set_bounds(BOX::new([-1.0, -1.0], [2.0, 2.0]));
+        ensure_no_self_intersections(&ob.free_space);
+        assert!((free_area(&ob.free_space) - (9.0 - 0.25)).abs() < 1e-5);
+        assert_eq!(ob.check_a_box(occluder), OcclusionStatus::Occluded);
+        assert_eq!(
+            ob.check_a_box(BOX::new([1.5, 1.5], [1.8, 1.8])),
+            OcclusionStatus::PartiallyVisible
+        );
+
+        let small = BOX::new([0.5, 0.5], [1.0, 1.0]);
+        ob.set_bounds(small);
+        assert!((free_area(&ob.free_space) - (0.25 - 0.04)).abs() < 1e-5);
+        assert!(ob.free_boxes().all(|b| small.encloses(b)));
+    }
+
+    #[test]
+    pub fn translate_and_scale() {
+        let occluder = BOX::new([0.2, 0.2], [0.4, 0.4]);
+        let mut ob = OcclusionBuffer::new(BOX::new([0.0, 0.0], [1.0, 1.0]));
+        ob.add_box_set(vec![occluder]);
+
+        ob.translate([0.5, 0.5]);
+        ensure_no_self_intersections(&ob.free_space);
+        assert!((free_area(&ob.free_space) - 0.96).abs() < 1e-5);
+        assert_eq!(
+            ob.check_a_box(BOX::new([0.75, 0.75], [0.85, 0.85])),
+            OcclusionStatus::Occluded
+        );
+        assert_eq!(ob.check_a_box(occluder), OcclusionStatus::PartiallyVisible);
+
+        // occluder now at [0.7, 0.9], half of it leaves the bounds
+        ob.scale([0.5, 0.5], [2.0, 1.0]);
+        ensure_no_self_intersections(&ob.free_space);
+        assert!((free_area(&ob.free_space) - (1.0 - 0.1 * 0.2)).abs() < 1e-5);
+        assert!(ob.free_boxes().all(|b| ob.bounds().encloses(b)));
+
+        ob.translate([5.0, 0.0]);
+        assert_eq!(free_area(&ob.free_space), 1.0);
+    }
+
+    #[test]
+    #[cfg(feature = "plot")]
+    pub fn svg_export_fits_viewport() {
+        let bounds = BOX::new([-10.0, -10.0], [10.0, 10.0]);
+        let proj = plot::Projection::fit(&bounds, (200, 100));
+        assert_eq!(proj.rect(&bounds, (200, 100)), Some(((50, 0), (150, 100))));
+        assert_eq!(
+            proj.rect(&BOX::new([0.0, 0.0], [5.0, 5.0]), (200, 100)),
+            Some(((100, 25), (125, 50)))
+        );
+        // off the canvas entirely, and partially
+        assert_eq!(
+            proj.rect(&BOX::new([30.0, 0.0], [35.0, 5.0]), (200, 100)),
+            None
+        );
+        assert_eq!(
+            proj.rect(&BOX::new([0.0, 0.0], [30.0, 30.0]), (200, 100)),
+            Some(((100, -1), (201, 50)))
+        );
+
+        let mut ob = OcclusionBuffer::new(bounds);
+        let occluder = BOX::new([-5.0, -5.0], [5.0, 5.0]);
+        ob.add_box_set(vec![occluder]);
+        let options = PlotOptions {
+            labels: true,
+            ..Default::default()
+        };
+        let svg = svg_string(&ob, &[occluder], &[], &options).unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("occluders"));
+        assert!(svg.matches("<rect").count() > ob.stats().fragments);
+        let flat = PlotOptions {
+            viewport: Some(BOX::new([0.0, 0.0], [5.0, 0.0])),
+            ..Default::default()
+        };
+        assert!(svg_string(&ob, &[], &[], &flat).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "plot")]
+    pub fn rgba_rendering() {
+        let mut ob = OcclusionBuffer::new(BOX::new([0.0, 0.0], [1.0, 1.0]));
+        // occluder covers the left half, touching the bounds
+        OcclusionQuery::insert(&mut ob, BOX::new([0.0, 0.0], [0.5, 1.0]));
+        let options = PlotOptions {
+            size: (40, 20),
+            legend: false,
+            ..Default::default()
+        };
+        let pixels = render_rgba(&ob, &[], &[], &options).unwrap();
+        assert_eq!(pixels.len(), 40 * 20 * 4);
+        let pixel = |x: usize, y: usize| &pixels[(y * 40 + x) * 4..(y * 40 + x) * 4 + 4];
+        // canvas is wider than bounds: free half is on the right of the centered 20x20 square
+        let free = options.free_space.fill.unwrap();
+        assert_eq!(pixel(25, 10), [free.r, free.g, free.b, 255]);
+        // background is transparent
+        assert_eq!(pixel(15, 10), [0, 0, 0, 0]);
+        assert_eq!(pixel(2, 10), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    #[cfg(feature = "plot")]
+    pub fn rgba_rendering_keeps_fill_alpha() {
+        let occluder = BOX::new([0.0, 0.0], [0.5, 1.0]);
+        let mut ob = OcclusionBuffer::new(BOX::new([0.0, 0.0], [1.0, 1.0]));
+        OcclusionQuery::insert(&mut ob, occluder);
+        let options = PlotOptions {
+            size: (40, 20),
+            legend: false,
+            ..Default::default()
+        };
+        let pixels = render_rgba(&ob, &[occluder], &[], &options).unwrap();
+        let fill = options.occluders.fill.unwrap();
+        let inside = &pixels[(10 * 40 + 15) * 4..(10 * 40 + 15) * 4 + 4];
+        assert!(
+            (inside[3] as f32 - fill.a * 255.0).abs() <= 2.0,
+            "{inside:?}"
+        );
+        assert!((inside[0] as i32 - fill.r as i32).abs() <= 2, "{inside:?}");
+    }
+
+    #[test]
+    #[cfg(feature = "plot")]
+    pub fn png_export() {
+        let mut ob = OcclusionBuffer::new(BOX::new([0.0, 0.0], [1.0, 1.0]));
+        ob.add_box_set(vec![BOX::new([0.2, 0.2], [0.5, 0.5])]);
+        let path = std::env::temp_dir().join(format!("aoc_png_export_{}.png", std::process::id()));
+        let path = path.to_str().unwrap();
+        let options = PlotOptions {
+            size: (64, 48),
+            ..Default::default()
+        };
+        export_png(&ob, &[], &[], path, &options).unwrap();
+        let bytes = std::fs::read(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert!(bytes.starts_with(b"\x89PNG"));
+        // IHDR holds width and height as big endian u32
+        assert_eq!(&bytes[16..24], &[0, 0, 0, 64, 0, 0, 0, 48]);
+    }
+
+    #[test]
+    pub fn trace_records_insertions() {
+        let mut ob = OcclusionBuffer::new(BOX::new([0.0, 0.0], [1.0, 1.0]));
+        ob.start_trace();
+        ob.add_box_set(vec![
+            BOX::new([0.2, 0.2], [0.4, 0.4]),
+            BOX::new([0.25, 0.25], [0.35, 0.35]),
+        ]);
+        let trace = ob.take_trace().unwrap();
+        assert!(ob.take_trace().is_none());
+        // two queries, one insertion
+        assert_eq!(trace.frames.len(), 3);
+        match &trace.frames[1].step {
+            TraceStep::Insert {
+                intersected,
+                produced,
+                ..
+            } => {
+                assert_eq!(intersected.len(), 1);
+                assert_eq!(produced.len(), 4);
+                assert_eq!(trace.frames[1].free_space.len(), 4);
+            }
+            s => panic!("Expected insertion, got {s:?}"),
+        }
+        match &trace.frames[2].step {
+            TraceStep::Query { status, .. } => assert_eq!(*status, OcclusionStatus::Occluded),
+            s => panic!("Expected query, got {s:?}"),
+        }
+
+        #[cfg(feature = "plot")]
+        {
+            let options = PlotOptions::default();
+            let svg = trace.to_animated_svg(&options, 0.5).unwrap();
+            assert_eq!(svg.matches("<animate ").count(), 3);
+            let html = trace.to_html(&options).unwrap();
+            assert_eq!(html.matches("class=\"frame\"").count(), 3);
+            assert!(trace.frame_svg(3, &options).is_err());
+        }
+    }
+
+    /// Replays a text log and checks consistency of the result.
+    /// Logs of failing sessions can be pasted here as regression tests.
+    fn replay_log(log: &str) -> OcclusionBuffer {
+        let ob = log.parse::<OpLog>().unwrap().replay().unwrap();
+        ensure_no_self_intersections(&ob.free_space);
+        ob
+    }
+
+    fn fragments(ob: &OcclusionBuffer) -> Vec<([f32; 4], usize)> {
+        ob.free_space
+            .boxes
+            .iter()
+            .map(|(b, i)| ([b.lo(0), b.lo(1), b.hi(0), b.hi(1)], *i))
+            .collect()
+    }
+
+    #[test]
+    pub fn record_and_replay() {
+        let mut ob = OcclusionBuffer::with_strategy(
+            BOX::new([-1.0, -1.0], [2.0, 2.0]),
+            SplitStrategy::MinFragments,
+        );
+        ob.add_box_set(vec![BOX::new([0.1, 0.1], [0.3, 0.7])]);
+        ob.start_recording();
+        ob.set_compaction_threshold(None);
+        ob.add_box_set(vec![
+            BOX::new([0.2, 0.2], [0.9, 0.4]),
+            BOX::new([0.15, 0.15], [0.25, 0.25]),
+            BOX::new([-0.3, 0.5], [0.12345679, 1.7]),
+        ]);
+        ob.translate([0.1, -0.05]);
+        ob.scale([0.5, 0.5], [1.5, 0.75]);
+        ob.compact();
+        OcclusionQuery::insert(&mut ob, BOX::new([-1.0, 1.5], [0.5, 2.0]));
+        ob.check_a_box(BOX::new([0.0, 0.0], [0.5, 0.5]));
+
+        let text = ob.recording().unwrap().to_string();
+        let count = text.lines().filter(|l| l.starts_with("fragment ")).count();
+        assert!(text.starts_with(&format!(
+            "new -1.0 -1.0 2.0 2.0 min_fragments 0.25\nfragments {count}\nfragment "
+        )));
+        let replayed = replay_log(&text);
+        // buffer was in use when recording started, so fragment ids differ
+        let area = |ob: &OcclusionBuffer| ob.stats().free_area;
+        assert!((area(&replayed) - area(&ob)).abs() < 1e-5);
+        assert_eq!(replayed.stats().fragments, ob.stats().fragments);
+        assert_eq!(replayed.occlusion_status.len(), ob.occlusion_status.len());
+
+        // recording a fresh buffer reproduces exact state
+        let mut ob = OcclusionBuffer::new(BOX::new([0.0, 0.0], [1.0, 1.0]));
+        ob.start_recording();
+        ob.add_box_set(vec![
+            BOX::new([0.0059359292, 0.018032173], [0.04272885, 0.05482509]),
+            BOX::new([0.01197005, 0.01], [0.04272885, 0.054460805]),
+            BOX::new([0.5, 0.5], [0.9, 0.7]),
+        ]);
+        let log = ob.take_recording().unwrap();
+        assert!(ob.recording().is_none());
+        let replayed = replay_log(&log.to_string());
+        assert_eq!(fragments(&replayed), fragments(&ob));
+        assert_eq!(replayed.dead_boxes, ob.dead_boxes);
+        assert_eq!(replayed.stats(), ob.stats());
+
+        assert!("check 0 0 1".parse::<OpLog>().is_err());
+        assert!("add\n".parse::<OpLog>().unwrap().replay().is_err());
+        let truncated = "new 0 0 1 1 guillotine none\nfragments 2\nfragment 0 0 1 0.5\n";
+        assert!(truncated.parse::<OpLog>().unwrap().replay().is_err());
+    }
+
+    #[test]
+    pub fn replay_fully_occluded_buffer() {
+        let mut ob = OcclusionBuffer::new(BOX::new([0.0, 0.0], [1.0, 1.0]));
+        OcclusionQuery::insert(&mut ob, BOX::new([-1.0, -1.0], [2.0, 2.0]));
+        assert_eq!(ob.free_boxes().count(), 0);
+        ob.start_recording();
+        ob.check_a_box(BOX::new([0.25, 0.25], [0.5, 0.5]));
+
+        let text = ob.recording().unwrap().to_string();
+        assert!(text.starts_with("new 0.0 0.0 1.0 1.0 guillotine 0.25\nfragments 0\n"));
+        let mut replayed = replay_log(&text);
+        let live = |ob: &OcclusionBuffer| {
+            ob.free_boxes()
+                .map(|b| [b.lo(0), b.lo(1), b.hi(0), b.hi(1)])
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(live(&replayed), live(&ob));
+        assert_eq!(
+            replayed.check_a_box(BOX::new([0.25, 0.25], [0.5, 0.5])),
+            OcclusionStatus::Occluded
+        );
+    }
+
+    #[test]
+    pub fn scene_formats() {
+        let mut scene = Scene::new(BOX::new([0.0, 0.0], [10.0, 10.0]));
+        let occluder = |id, lo, hi, depth, role| SceneOccluder {
+            id,
+            lo,
+            hi,
+            depth,
+            role,
+        };
+        scene.occluders = vec![
+            occluder(1, [2.0, 2.0], [3.0, 3.0], 2.0, Role::Object),
+            occluder(2, [1.0, 1.0], [4.0, 4.0], 1.0, Role::Object),
+            occluder(3, [0.5, 6.0], [9.5, 9.5], 0.5, Role::Occluder),
+            occluder(4, [5.0, 7.0], [6.0, 9.0], 3.0, Role::Object),
+        ];
+        scene.queries = vec![
+            SceneQuery {
+                id: 10,
+                lo: [1.5, 1.5],
+                hi: [2.5, 2.5],
+            },
+            SceneQuery {
+                id: 11,
+                lo: [5.0, 1.0],
+                hi: [6.0, 2.0],
+            },
+        ];
+
+        assert_eq!(Scene::from_bytes(&scene.to_bytes()).unwrap(), scene);
+        #[cfg(feature = "serde")]
+        {
+            assert_eq!(Scene::from_json(&scene.to_json()).unwrap(), scene);
+            let minimal = r#"{"version": 1, "bounds": [[0, 0], [1, 1]], "occluders": [{"id": 7, "lo": [0, 0], "hi": [1, 1]}]}"#;
+            let minimal = Scene::from_json(minimal).unwrap();
+            assert_eq!(minimal.occluders[0].role, Role::Object);
+            assert!(minimal.queries.is_empty());
+            // an occluder covering the bounds is a normal scene
+            let (buffer, visibility) = minimal.run(SplitStrategy::default()).unwrap();
+            assert_eq!(visibility, vec![(7, OcclusionStatus::PartiallyVisible)]);
+            assert_eq!(buffer.stats().free_area, 0.0);
+            assert!(Scene::from_json(r#"{"version": 2, "bounds": [[0, 0], [1, 1]]}"#).is_err());
+        }
+        let bytes = scene.to_bytes();
+        assert!(Scene::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Scene::from_bytes(&[bytes.clone(), bytes.clone()].concat()).is_err());
+        assert!(Scene::from_bytes(&[bytes, vec![0]].concat()).is_err());
+
+        // boxes may lie on the bounds, but not cross them
+        let mut on_bounds = Scene::new(BOX::new([0.0, 0.0], [4.0, 4.0]));
+        let occluder = |id, lo, hi| SceneOccluder {
+            id,
+            lo,
+            hi,
+            depth: 0.0,
+            role: Role::Object,
+        };
+        on_bounds.occluders = vec![
+            occluder(1, [0.0, 0.0], [4.0, 2.0]),
+            occluder(2, [1.0, 0.0], [2.0, 1.0]),
+            occluder(3, [0.0, 2.0], [1.0, 4.0]),
+        ];
+        on_bounds.queries = vec![SceneQuery {
+            id: 4,
+            lo: [1.0, 2.0],
+            hi: [4.0, 4.0],
+        }];
+        let (_, visibility) = on_bounds.run(SplitStrategy::default()).unwrap();
+        use OcclusionStatus::*;
+        assert_eq!(
+            visibility,
+            vec![
+                (1, PartiallyVisible),
+                (2, Occluded),
+                (3, PartiallyVisible),
+                (4, PartiallyVisible)
+            ]
+        );
+        on_bounds
+            .occluders
+            .push(occluder(5, [3.0, 3.0], [5.0, 4.0]));
+        assert!(on_bounds.run(SplitStrategy::default()).is_err());
+
+        let (_, visibility) = scene.run(SplitStrategy::default()).unwrap();
+        assert_eq!(
+            visibility,
+            vec![
+                (2, PartiallyVisible),
+                (1, Occluded),
+                (4, Occluded),
+                (10, Occluded),
+                (11, PartiallyVisible)
+            ]
+        );
+    }
+
+    #[test]
+    pub fn geojson_and_wkt() {
+        let bounds = BOX::new([0.0, 0.0], [4.0, 2.0]);
+        let mut ob = OcclusionBuffer::new(bounds);
+        ob.add_box_set(vec![
+            BOX::new([1.0, 0.5], [2.0, 1.5]),
+            BOX::new([2.5, 0.25], [3.5, 1.0]),
+        ]);
+        let free_area = |ob: &OcclusionBuffer| ob.stats().free_area;
+
+        let wkt = free_space_wkt(&ob);
+        assert!(wkt.starts_with("MULTIPOLYGON (((0 "));
+        assert_eq!(wkt.matches("((").count(), ob.stats().fragments);
+        let from_wkt = buffer_from_wkt(&wkt, Some(bounds), SplitStrategy::default()).unwrap();
+        assert_eq!(free_area(&from_wkt), free_area(&ob));
+        assert_eq!(from_wkt.stats().fragments, ob.stats().fragments);
+        assert_eq!(
+            buffer_from_wkt(&occupied_wkt(&ob), None, SplitStrategy::default())
+                .unwrap()
+                .stats()
+                .free_area,
+            1.0 + 0.75
+        );
+
+        // occupied features are inserted as occluders on top of free space
+        #[cfg(feature = "serde")]
+        {
+            let geojson = free_space_geojson(&ob, true);
+            let from_geojson = buffer_from_geojson(&geojson, SplitStrategy::default()).unwrap();
+            assert_eq!(from_geojson.bounds().area(), bounds.area());
+            assert_eq!(free_area(&from_geojson), free_area(&ob));
+            let full =
+                r#"{"type": "Polygon", "coordinates": [[[0, 0], [0, 1], [1, 1], [1, 0], [0, 0]]]}"#;
+            assert_eq!(
+                free_area(&buffer_from_geojson(full, SplitStrategy::default()).unwrap()),
+                1.0
+            );
+        }
+
+        let triangle = "POLYGON ((0 0, 1 0, 1 1, 0 0))";
+        assert!(buffer_from_wkt(triangle, None, SplitStrategy::default()).is_err());
+        let hole = "POLYGON ((0 0, 4 0, 4 4, 0 4, 0 0), (1 1, 2 1, 2 2, 1 2, 1 1))";
+        assert!(buffer_from_wkt(hole, None, SplitStrategy::default()).is_err());
+        let overlap = "MULTIPOLYGON (((0 0, 2 0, 2 2, 0 2, 0 0)), ((1 1, 3 1, 3 3, 1 3, 1 1)))";
+        assert!(buffer_from_wkt(overlap, None, SplitStrategy::default()).is_err());
+        assert_eq!(
+            buffer_from_wkt("MULTIPOLYGON EMPTY", Some(bounds), SplitStrategy::default())
+                .unwrap()
+                .stats()
+                .fragments,
+            0
+        );
+    }
+
+    #[test]
+    pub fn occupied_boxes_complement_free_space() {
+        let bounds = BOX::new([0.0, 0.0], [2.0, 2.0]);
+        let mut ob = OcclusionBuffer::new(bounds);
+        ob.add_box_set(vec![
+            BOX::new([0.5, 0.5], [1.0, 1.5]),
+            BOX::new([0.75, 1.0], [1.5, 1.25]),
+        ]);
+        let occupied = ob.occupied_boxes();
+        let area: f32 = occupied.iter().map(|b| b.area()).sum();
+        assert!((area - (0.5 + 0.5 * 0.25)).abs() < 1e-6);
+        for o in &occupied {
+            assert!(bounds.encloses(o));
+            assert!(ob.free_boxes().all(|f| !f.overlaps_with(o)));
+        }
+    }
+
+    #[test]
+    pub fn payloads_of_covering_occluders() {
+        let mut pb = PayloadBuffer::new(BOX::new([0.0, 0.0], [10.0, 10.0]));
+        assert_eq!(
+            pb.cull(BOX::new([1.0, 1.0], [4.0, 4.0]), "house"),
+            OcclusionStatus::PartiallyVisible
+        );
+        assert_eq!(
+            pb.cull(BOX::new([2.0, 2.0], [3.0, 3.0]), "hidden"),
+            OcclusionStatus::Occluded
+        );
+        pb.cull(BOX::new([3.0, 3.0], [8.0, 5.0]), "wall");
+        pb.insert(BOX::new([9.0, 9.0], [11.0, 11.0]), "sign");
+        assert_eq!(pb.occluders().len(), 3);
+        assert_eq!(
+            pb.covering(&BOX::new([3.5, 3.5], [4.5, 4.5])),
+            [&"house", &"wall"]
+        );
+        assert!(pb.covering(&BOX::new([5.0, 6.0], [6.0, 7.0])).is_empty());
+        let inside: Vec<_> = pb
+            .occluders_in(&BOX::new([0.0, 0.0], [5.0, 5.0]))
+            .iter()
+            .map(|(_, p)| *p)
+            .collect();
+        assert_eq!(inside, ["house"]);
+
+        pb.translate([1.0, 0.0]);
+        assert_eq!(
+            pb.covering(&BOX::new([1.5, 1.5], [1.8, 1.8])),
+            Vec::<&&str>::new()
+        );
+        assert_eq!(pb.covering(&BOX::new([2.5, 1.5], [2.8, 1.8])), [&"house"]);
+        assert_eq!(
+            pb.query(&BOX::new([2.5, 1.5], [2.8, 1.8])),
+            OcclusionStatus::Occluded
+        );
+        // the sign left the bounds and was dropped, the rest is clipped to them
+        let boxes = |pb: &PayloadBuffer<&'static str>| {
+            pb.occluders()
+                .iter()
+                .map(|(b, p)| (b.lo(0), b.lo(1), b.hi(0), b.hi(1), *p))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            boxes(&pb),
+            [(2.0, 1.0, 5.0, 4.0, "house"), (4.0, 3.0, 9.0, 5.0, "wall")]
+        );
+        pb.scale([0.0, 0.0], [2.0, 2.0]);
+        assert_eq!(
+            boxes(&pb),
+            [
+                (4.0, 2.0, 10.0, 8.0, "house"),
+                (8.0, 6.0, 10.0, 10.0, "wall")
+            ]
+        );
+        pb.set_bounds(BOX::new([0.0, 0.0], [6.0, 10.0]));
+        assert_eq!(boxes(&pb), [(4.0, 2.0, 6.0, 8.0, "house")]);
+        pb.reset();
+        assert!(pb.occluders().is_empty());
+        assert_eq!(pb.buffer().stats().fragments, 1);
+    }
+
     fn better_name(s: &str) -> String {
-        s.to_string().split("::").last().unwrap().to_string()
+        s.split("::").last().unwrap().to_string()
     }
 
     #[test]
     pub fn edge_case() {
-        let cases = vec![
+        let cases = [
             BOX::new([0.0, 0.0], [1.0, 0.5]),  //left_side
             BOX::new([-0.2, 0.2], [1.0, 0.6]), //right_side
             BOX::new([0.2, -0.2], [0.6, 1.0]), // top_side
@@ -0,0 +1,94 @@
+use crate::box_cutting::*;
+use box_intersect_ze::boxes::BBox;
+
+/// Free space stored as the set of all maximal empty rectangles (as used in bin-packing).
+/// Unlike OcclusionBuffer, rectangles may overlap, so any box that fits into free space
+/// fits entirely into one of them.
+#[derive(Clone, Debug)]
+pub struct MaxRectsBuffer {
+    bounds: BOX,
+    free: Vec<BOX>,
+}
+
+impl MaxRectsBuffer {
+    pub fn new(bounds: BOX) -> Self {
+        MaxRectsBuffer {
+            bounds,
+            free: vec![bounds],
+        }
+    }
+
+    pub fn bounds(&self) -> BOX {
+        self.bounds
+    }
+
+    /// Maximal empty rectangles, these may overlap each other
+    pub fn free_rects(&self) -> &[BOX] {
+        &self.free
+    }
+
+    /// Checks if any part of the box is still free
+    pub fn is_visible(&self, b: &BOX) -> bool {
+        self.free.iter().any(|f| f.overlaps_with(b))
+    }
+
+    /// Marks box as occupied, replacing every rectangle it overlaps with up to 4 maximal ones
+    pub fn occlude(&mut self, b: BOX) {
+        let mut produced = vec![];
+        self.free.retain(|f| {
+            if !f.overlaps_with(&b) {
+                return true;
+            }
+            produced.extend(maximal_remainder(f, &b));
+            false
+        });
+        self.free.extend(produced);
+        self.prune();
+    }
+
+    /// Finds a spot for a w×h box using best short side fit,
+    /// returns the box placed in the lower left corner of the chosen rectangle.
+    pub fn find_fit(&self, w: f32, h: f32) -> Option<BOX> {
+        let leftover = |f: &BOX| ((f.hi(0) - f.lo(0)) - w).min((f.hi(1) - f.lo(1)) - h);
+        self.free
+            .iter()
+            .filter(|f| leftover(f) >= 0.0)
+            .min_by(|a, b| leftover(a).total_cmp(&leftover(b)))
+            .map(|f| BOX::new([f.lo(0), f.lo(1)], [f.lo(0) + w, f.lo(1) + h]))
+    }
+
+    /// Removes rectangles that lie within other rectangles (including duplicates)
+    fn prune(&mut self) {
+        let mut i = 0;
+        'outer: while i < self.free.len() {
+            let mut j = i + 1;
+            while j < self.free.len() {
+                if self.free[j].encloses(&self.free[i]) {
+                    self.free.swap_remove(i);
+                    continue 'outer;
+                }
+                if self.free[i].encloses(&self.free[j]) {
+                    self.free.swap_remove(j);
+                } else {
+                    j += 1;
+                }
+            }
+            i += 1;
+        }
+    }
+}
+
+/// Maximal rectangles of free that do not overlap cut (left, right, below, above)
+fn maximal_remainder(free: &BOX, cut: &BOX) -> impl Iterator<Item = BOX> {
+    // clip cut to free so that none of the produced boxes are inverted
+    let lo = [cut.lo(0).max(free.lo(0)), cut.lo(1).max(free.lo(1))];
+    let hi = [cut.hi(0).min(free.hi(0)), cut.hi(1).min(free.hi(1))];
+    [
+        BOX::new([free.lo(0), free.lo(1)], [lo[0], free.hi(1)]),
+        BOX::new([hi[0], free.lo(1)], [free.hi(0), free.hi(1)]),
+        BOX::new([free.lo(0), free.lo(1)], [free.hi(0), lo[1]]),
+        BOX::new([free.lo(0), hi[1]], [free.hi(0), free.hi(1)]),
+    ]
+    .into_iter()
+    .filter(|b| !b.is_empty())
+}
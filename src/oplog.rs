@@ -0,0 +1,277 @@
+use std::fmt;
+use std::str::FromStr;
+
+use box_intersect_ze::boxes::BBox;
+
+use crate::box_cutting::*;
+use crate::OcclusionBuffer;
+
+/// Operation that changes state of OcclusionBuffer, as recorded by OcclusionBuffer::start_recording
+#[derive(Clone, Copy, Debug)]
+pub enum Op {
+    /// Buffer construction, always the first operation of a log
+    New {
+        bounds: BOX,
+        strategy: SplitStrategy,
+        compaction_threshold: Option<f32>,
+    },
+    /// Recording started on a buffer that was already in use: number of Fragment operations
+    /// that follow, possibly zero, which replace the initial free space. Directly follows New.
+    Fragments(usize),
+    /// Fragment of free space at the start of recording, see Fragments
+    Fragment(BOX),
+    Check(BOX),
+    /// OcclusionQuery::query, like Check but the box may touch or cross the bounds
+    Query(BOX),
+    /// Query without bounds check, as done by OcclusionQuery::insert
+    Intersect(BOX),
+    AddLastBox,
+    Compact,
+    SetCompactionThreshold(Option<f32>),
+    Reset,
+    SetBounds(BOX),
+    Translate([f32; 2]),
+    Scale {
+        center: [f32; 2],
+        factors: [f32; 2],
+    },
+}
+
+/// Sequence of operations that reproduces buffer state.
+/// Text form has one operation per line, floats are written in shortest form that parses back exactly.
+#[derive(Clone, Debug, Default)]
+pub struct OpLog {
+    pub ops: Vec<Op>,
+}
+
+impl OpLog {
+    /// Starts a log for buffer in its current state
+    pub(crate) fn of_buffer(buffer: &OcclusionBuffer) -> Self {
+        let mut ops = vec![Op::New {
+            bounds: buffer.overall_bound_box,
+            strategy: buffer.split_strategy,
+            compaction_threshold: buffer.compaction_threshold,
+        }];
+        let fresh = buffer.free_space.boxes.len() == 1
+            && buffer.free_space.boxes[0]
+                .0
+                .encloses(&buffer.overall_bound_box);
+        if !fresh {
+            ops.push(Op::Fragments(buffer.free_boxes().count()));
+            ops.extend(buffer.free_boxes().map(|b| Op::Fragment(*b)));
+        }
+        Self { ops }
+    }
+
+    /// Runs all operations on a new buffer. Panics of the buffer itself are not caught,
+    /// so replaying a log of a crashed session crashes at the same operation.
+    pub fn replay(&self) -> Result<OcclusionBuffer, String> {
+        let (bounds, strategy, threshold) = match self.ops.first() {
+            Some(&Op::New {
+                bounds,
+                strategy,
+                compaction_threshold,
+            }) => (bounds, strategy, compaction_threshold),
+            _ => return Err("Log should start with buffer construction".to_owned()),
+        };
+        let (mut buffer, start) = match self.ops.get(1) {
+            Some(&Op::Fragments(count)) => {
+                let fragments = self.ops[2..]
+                    .iter()
+                    .take(count)
+                    .map(|op| match op {
+                        Op::Fragment(b) => Some(*b),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<BOX>>>()
+                    .filter(|f| f.len() == count)
+                    .ok_or_else(|| format!("Log should list {count} free space fragments"))?;
+                (
+                    OcclusionBuffer::from_free_space(bounds, fragments, strategy),
+                    2 + count,
+                )
+            }
+            _ => (OcclusionBuffer::with_strategy(bounds, strategy), 1),
+        };
+        buffer.set_compaction_threshold(threshold);
+        for (i, op) in self.ops.iter().enumerate().skip(start) {
+            match *op {
+                Op::New { .. } | Op::Fragments(_) | Op::Fragment(_) => {
+                    return Err(format!("Unexpected {op:?} at operation {i}"));
+                }
+                Op::Check(b) => {
+                    buffer.check_a_box(b);
+                }
+                Op::Query(b) => {
+                    buffer.run_query(b);
+                }
+                Op::Intersect(b) => {
+                    buffer.find_intersections(b);
+                }
+                Op::AddLastBox => buffer.add_last_box(),
+                Op::Compact => buffer.compact(),
+                Op::SetCompactionThreshold(t) => buffer.set_compaction_threshold(t),
+                Op::Reset => buffer.reset(),
+                Op::SetBounds(b) => buffer.set_bounds(b),
+                Op::Translate(offset) => buffer.translate(offset),
+                Op::Scale { center, factors } => buffer.scale(center, factors),
+            }
+        }
+        Ok(buffer)
+    }
+}
+
+fn write_box(f: &mut fmt::Formatter<'_>, b: &BOX) -> fmt::Result {
+    write!(
+        f,
+        " {:?} {:?} {:?} {:?}",
+        b.lo(0),
+        b.lo(1),
+        b.hi(0),
+        b.hi(1)
+    )
+}
+
+fn write_threshold(f: &mut fmt::Formatter<'_>, t: Option<f32>) -> fmt::Result {
+    match t {
+        Some(t) => write!(f, " {t:?}"),
+        None => write!(f, " none"),
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Op::New {
+                bounds,
+                strategy,
+                compaction_threshold,
+            } => {
+                write!(f, "new")?;
+                write_box(f, bounds)?;
+                write!(f, " {strategy}")?;
+                write_threshold(f, *compaction_threshold)
+            }
+            Op::Fragments(count) => write!(f, "fragments {count}"),
+            Op::Fragment(b) => {
+                write!(f, "fragment")?;
+                write_box(f, b)
+            }
+            Op::Check(b) => {
+                write!(f, "check")?;
+                write_box(f, b)
+            }
+            Op::Query(b) => {
+                write!(f, "query")?;
+                write_box(f, b)
+            }
+            Op::Intersect(b) => {
+                write!(f, "intersect")?;
+                write_box(f, b)
+            }
+            Op::AddLastBox => write!(f, "add"),
+            Op::Compact => write!(f, "compact"),
+            Op::SetCompactionThreshold(t) => {
+                write!(f, "threshold")?;
+                write_threshold(f, *t)
+            }
+            Op::Reset => write!(f, "reset"),
+            Op::SetBounds(b) => {
+                write!(f, "bounds")?;
+                write_box(f, b)
+            }
+            Op::Translate([x, y]) => write!(f, "translate {x:?} {y:?}"),
+            Op::Scale { center, factors } => write!(
+                f,
+                "scale {:?} {:?} {:?} {:?}",
+                center[0], center[1], factors[0], factors[1]
+            ),
+        }
+    }
+}
+
+impl fmt::Display for OpLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for op in &self.ops {
+            writeln!(f, "{op}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Op {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut words = line.split_whitespace();
+        let name = words.next().ok_or("Empty operation")?;
+        let args: Vec<&str> = words.collect();
+        let floats = |n: usize| -> Result<Vec<f32>, String> {
+            if args.len() < n {
+                return Err(format!("{name} expects {n} numbers in {line:?}"));
+            }
+            args[..n]
+                .iter()
+                .map(|a| a.parse::<f32>().map_err(|e| format!("{e} in {line:?}")))
+                .collect()
+        };
+        let threshold = |a: Option<&&str>| -> Result<Option<f32>, String> {
+            match a {
+                Some(&"none") => Ok(None),
+                Some(a) => a.parse().map(Some).map_err(|e| format!("{e} in {line:?}")),
+                None => Err(format!("Missing compaction threshold in {line:?}")),
+            }
+        };
+        let bx = |v: &[f32]| BOX::new([v[0], v[1]], [v[2], v[3]]);
+        let op = match name {
+            "new" => Op::New {
+                bounds: bx(&floats(4)?),
+                strategy: args.get(4).ok_or("Missing split strategy")?.parse()?,
+                compaction_threshold: threshold(args.get(5))?,
+            },
+            "fragments" => Op::Fragments(
+                args.first()
+                    .ok_or_else(|| format!("Missing fragment count in {line:?}"))?
+                    .parse()
+                    .map_err(|e| format!("{e} in {line:?}"))?,
+            ),
+            "fragment" => Op::Fragment(bx(&floats(4)?)),
+            "check" => Op::Check(bx(&floats(4)?)),
+            "query" => Op::Query(bx(&floats(4)?)),
+            "intersect" => Op::Intersect(bx(&floats(4)?)),
+            "add" => Op::AddLastBox,
+            "compact" => Op::Compact,
+            "threshold" => Op::SetCompactionThreshold(threshold(args.first())?),
+            "reset" => Op::Reset,
+            "bounds" => Op::SetBounds(bx(&floats(4)?)),
+            "translate" => {
+                let v = floats(2)?;
+                Op::Translate([v[0], v[1]])
+            }
+            "scale" => {
+                let v = floats(4)?;
+                Op::Scale {
+                    center: [v[0], v[1]],
+                    factors: [v[2], v[3]],
+                }
+            }
+            other => return Err(format!("Unknown operation {other:?}")),
+        };
+        Ok(op)
+    }
+}
+
+impl FromStr for OpLog {
+    type Err = String;
+
+    /// Parses text form, empty lines and lines starting with # are skipped
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ops = s
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(str::parse)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { ops })
+    }
+}
@@ -0,0 +1,150 @@
+//! Occluders tagged with caller data, for finding out what covers a region.
+//! Free space fragments keep their allocator indices, payloads live in a separate list.
+use box_intersect_ze::boxes::BBox;
+
+use crate::box_cutting::*;
+use crate::{OcclusionBuffer, OcclusionQuery, OcclusionStatus};
+
+/// OcclusionBuffer that remembers inserted occluders together with a payload (e.g. entity id).
+/// Occluders are kept in insertion order, lookups scan them linearly.
+#[derive(Clone)]
+pub struct PayloadBuffer<T> {
+    buffer: OcclusionBuffer,
+    occluders: Vec<(BOX, T)>,
+}
+
+impl<T> PayloadBuffer<T> {
+    pub fn new(bounds: BOX) -> Self {
+        Self::with_strategy(bounds, SplitStrategy::default())
+    }
+
+    pub fn with_strategy(bounds: BOX, strategy: SplitStrategy) -> Self {
+        Self {
+            buffer: OcclusionBuffer::with_strategy(bounds, strategy),
+            occluders: vec![],
+        }
+    }
+
+    /// Underlying buffer, for queries, statistics and export
+    pub fn buffer(&self) -> &OcclusionBuffer {
+        &self.buffer
+    }
+
+    pub fn into_parts(self) -> (OcclusionBuffer, Vec<(BOX, T)>) {
+        (self.buffer, self.occluders)
+    }
+
+    /// Checks the box with OcclusionBuffer::check_a_box, the same check cull does
+    pub fn query(&mut self, b: &BOX) -> OcclusionStatus {
+        self.buffer.check_a_box(*b)
+    }
+
+    /// Checks the box and adds it with payload if partially visible,
+    /// payloads of occluded boxes are dropped
+    pub fn cull(&mut self, b: BOX, payload: T) -> OcclusionStatus {
+        let status = self.buffer.cull_box(b);
+        if status == OcclusionStatus::PartiallyVisible {
+            self.occluders.push((b, payload));
+        }
+        status
+    }
+
+    /// Marks the box as occupied and remembers it, even if it was occluded already.
+    /// The box may touch or cross the bounds.
+    pub fn insert(&mut self, b: BOX, payload: T) {
+        self.buffer.insert(b);
+        self.occluders.push((b, payload));
+    }
+
+    /// All occluders with payloads, in insertion order
+    pub fn occluders(&self) -> &[(BOX, T)] {
+        &self.occluders
+    }
+
+    /// Payloads of occluders sharing area with the box, in insertion order
+    pub fn covering(&self, b: &BOX) -> Vec<&T> {
+        covering(&self.occluders, *b).map(|(_, p)| p).collect()
+    }
+
+    /// Occluders lying entirely within region, in insertion order
+    pub fn occluders_in(&self, region: &BOX) -> Vec<&(BOX, T)> {
+        enclosed_by(&self.occluders, *region).collect()
+    }
+
+    /// Restores full free space and forgets all occluders
+    pub fn reset(&mut self) {
+        self.buffer.reset();
+        self.occluders.clear();
+    }
+
+    /// Changes bounds, see OcclusionBuffer::set_bounds.
+    /// Occluders are clipped to the new bounds as in translate.
+    pub fn set_bounds(&mut self, bounds: BOX) {
+        self.buffer.set_bounds(bounds);
+        clip_boxes(&mut self.occluders, bounds);
+    }
+
+    /// Moves occluders by offset, see OcclusionBuffer::translate.
+    /// Occluders are clipped to bounds, those leaving them are dropped with their payloads.
+    pub fn translate(&mut self, offset: [f32; 2]) {
+        self.buffer.translate(offset);
+        translate_boxes(&mut self.occluders, self.buffer.bounds(), offset);
+    }
+
+    /// Scales occluders around center, see OcclusionBuffer::scale.
+    /// Occluders are clipped to bounds as in translate.
+    pub fn scale(&mut self, center: [f32; 2], factors: [f32; 2]) {
+        self.buffer.scale(center, factors);
+        scale_boxes(&mut self.occluders, self.buffer.bounds(), center, factors);
+    }
+}
+
+pub(crate) fn covering<T>(occluders: &[(BOX, T)], b: BOX) -> impl Iterator<Item = &(BOX, T)> {
+    occluders.iter().filter(move |(o, _)| o.overlaps_with(&b))
+}
+
+pub(crate) fn enclosed_by<T>(
+    occluders: &[(BOX, T)],
+    region: BOX,
+) -> impl Iterator<Item = &(BOX, T)> {
+    occluders.iter().filter(move |(o, _)| region.encloses(o))
+}
+
+/// Applies per-axis transform to occluders and clips them to bounds,
+/// dropping those left without area inside the bounds
+fn map_boxes<T>(occluders: &mut Vec<(BOX, T)>, bounds: BOX, f: impl Fn(f32, usize) -> f32) {
+    occluders.retain_mut(|(b, _)| {
+        let lo = [
+            f(b.lo(0), 0).max(bounds.lo(0)),
+            f(b.lo(1), 1).max(bounds.lo(1)),
+        ];
+        let hi = [
+            f(b.hi(0), 0).min(bounds.hi(0)),
+            f(b.hi(1), 1).min(bounds.hi(1)),
+        ];
+        let inside = lo[0] < hi[0] && lo[1] < hi[1];
+        if inside {
+            *b = BOX::new(lo, hi);
+        }
+        inside
+    });
+}
+
+pub(crate) fn clip_boxes<T>(occluders: &mut Vec<(BOX, T)>, bounds: BOX) {
+    map_boxes(occluders, bounds, |p, _| p);
+}
+
+pub(crate) fn translate_boxes<T>(occluders: &mut Vec<(BOX, T)>, bounds: BOX, offset: [f32; 2]) {
+    map_boxes(occluders, bounds, |p, d| p + offset[d]);
+}
+
+pub(crate) fn scale_boxes<T>(
+    occluders: &mut Vec<(BOX, T)>,
+    bounds: BOX,
+    center: [f32; 2],
+    factors: [f32; 2],
+) {
+    map_boxes(occluders, bounds, |p, d| {
+        center[d] + (p - center[d]) * factors[d]
+    });
+}
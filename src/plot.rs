@@ -0,0 +1,330 @@
+use box_intersect_ze::boxes::BBox;
+use plotters::prelude::*;
+use plotters::style::RGBAColor;
+
+use crate::box_cutting::*;
+use crate::OcclusionBuffer;
+
+const FONT_SIZE: f64 = 10.0;
+const LEGEND_ROW: i32 = 14;
+
+/// RGB colour with alpha in 0..=1
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: f32,
+}
+
+impl Color {
+    pub const fn rgba(r: u8, g: u8, b: u8, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+}
+
+impl From<Color> for RGBAColor {
+    fn from(c: Color) -> Self {
+        RGBAColor(c.r, c.g, c.b, c.a as f64)
+    }
+}
+
+/// Fill and outline of one kind of boxes, None skips that part
+#[derive(Clone, Copy, Debug)]
+pub struct BoxStyle {
+    pub fill: Option<Color>,
+    pub stroke: Option<Color>,
+}
+
+/// What and how to draw, default fits the view to buffer bounds on a 512x512 canvas
+#[derive(Clone, Debug)]
+pub struct PlotOptions {
+    /// Area of the scene to show, buffer bounds if None. Aspect ratio is preserved.
+    pub viewport: Option<BOX>,
+    /// Canvas size in pixels
+    pub size: (u32, u32),
+    pub free_space: BoxStyle,
+    pub occluders: BoxStyle,
+    pub queries: BoxStyle,
+    /// Draw fragment ids and occluder/query indices
+    pub labels: bool,
+    pub legend: bool,
+}
+
+impl Default for PlotOptions {
+    fn default() -> Self {
+        Self {
+            viewport: None,
+            size: (512, 512),
+            free_space: BoxStyle {
+                fill: Some(Color::rgba(227, 242, 253, 1.0)),
+                stroke: Some(Color::rgba(0, 0, 255, 1.0)),
+            },
+            occluders: BoxStyle {
+                fill: Some(Color::rgba(128, 128, 128, 0.4)),
+                stroke: Some(Color::rgba(64, 64, 64, 1.0)),
+            },
+            queries: BoxStyle {
+                fill: None,
+                stroke: Some(Color::rgba(255, 0, 0, 1.0)),
+            },
+            labels: false,
+            legend: true,
+        }
+    }
+}
+
+/// Maps scene coordinates to pixels, y axis pointing up in the scene and down on the canvas
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Projection {
+    origin: [f32; 2],
+    offset: [f32; 2],
+    scale: f32,
+}
+
+impl Projection {
+    /// Fits viewport into canvas keeping aspect ratio, centered
+    pub(crate) fn fit(viewport: &BOX, size: (u32, u32)) -> Self {
+        let (w, h) = (size.0 as f32, size.1 as f32);
+        let (vw, vh) = (extent(viewport, 0), extent(viewport, 1));
+        let scale = (w / vw).min(h / vh);
+        Self {
+            origin: [viewport.lo(0), viewport.hi(1)],
+            offset: [(w - vw * scale) / 2.0, (h - vh * scale) / 2.0],
+            scale,
+        }
+    }
+
+    /// Pixel corners (upper left, bottom right) of a box, None if it misses the canvas.
+    /// Boxes partially on the canvas are clamped slightly beyond it, so cut edges are not drawn.
+    pub(crate) fn rect(&self, b: &BOX, size: (u32, u32)) -> Option<((i32, i32), (i32, i32))> {
+        let px = |v: f32, d: usize| match d {
+            0 => self.offset[0] + (v - self.origin[0]) * self.scale,
+            _ => self.offset[1] + (self.origin[1] - v) * self.scale,
+        };
+        let lo = [px(b.lo(0), 0), px(b.hi(1), 1)];
+        let hi = [px(b.hi(0), 0), px(b.lo(1), 1)];
+        let limit = [size.0 as f32, size.1 as f32];
+        if (0..2).any(|d| hi[d] < 0.0 || lo[d] > limit[d]) {
+            return None;
+        }
+        let clamp = |p: f32, d: usize| p.round().clamp(-1.0, limit[d] + 1.0) as i32;
+        Some((
+            (clamp(lo[0], 0), clamp(lo[1], 1)),
+            (clamp(hi[0], 0), clamp(hi[1], 1)),
+        ))
+    }
+}
+
+fn extent(b: &BOX, d: usize) -> f32 {
+    b.hi(d) - b.lo(d)
+}
+
+fn msg<E: ToString>(e: E) -> String {
+    e.to_string()
+}
+
+/// Named group of boxes drawn with the same style, labeled with their ids
+pub(crate) struct Layer<'a> {
+    pub name: &'a str,
+    pub style: BoxStyle,
+    pub boxes: Vec<(BOX, usize)>,
+}
+
+/// Free space of the buffer, occluders and queries, numbered by their position
+fn buffer_layers(
+    buffer: &OcclusionBuffer,
+    occluders: &[BOX],
+    queries: &[BOX],
+    options: &PlotOptions,
+) -> Vec<Layer<'static>> {
+    let numbered =
+        |boxes: &[BOX]| -> Vec<(BOX, usize)> { boxes.iter().copied().zip(0..).collect() };
+    vec![
+        Layer {
+            name: "free space",
+            style: options.free_space,
+            boxes: buffer
+                .free_space
+                .boxes
+                .iter()
+                .filter(|(b, _)| b.lo(0) != NOWHERE)
+                .copied()
+                .collect(),
+        },
+        Layer {
+            name: "occluders",
+            style: options.occluders,
+            boxes: numbered(occluders),
+        },
+        Layer {
+            name: "queries",
+            style: options.queries,
+            boxes: numbered(queries),
+        },
+    ]
+}
+
+/// Writes buffer free space, occluders and query boxes into an svg file
+pub fn export_svg(
+    buffer: &OcclusionBuffer,
+    occluders: &[BOX],
+    queries: &[BOX],
+    path: &str,
+    options: &PlotOptions,
+) -> Result<(), String> {
+    let mut backend = SVGBackend::new(path, options.size);
+    let layers = buffer_layers(buffer, occluders, queries, options);
+    draw(
+        &mut backend,
+        buffer.overall_bound_box,
+        &layers,
+        options,
+        &WHITE,
+    )
+}
+
+/// Same as export_svg, but returns svg document as a string
+pub fn svg_string(
+    buffer: &OcclusionBuffer,
+    occluders: &[BOX],
+    queries: &[BOX],
+    options: &PlotOptions,
+) -> Result<String, String> {
+    let layers = buffer_layers(buffer, occluders, queries, options);
+    layers_svg(buffer.overall_bound_box, &layers, options)
+}
+
+/// Svg document with given layers
+pub(crate) fn layers_svg(
+    bounds: BOX,
+    layers: &[Layer],
+    options: &PlotOptions,
+) -> Result<String, String> {
+    let mut svg = String::new();
+    {
+        let mut backend = SVGBackend::with_string(&mut svg, options.size);
+        draw(&mut backend, bounds, layers, options, &WHITE)?;
+    }
+    Ok(svg)
+}
+
+/// Writes buffer free space, occluders and query boxes into a png file
+pub fn export_png(
+    buffer: &OcclusionBuffer,
+    occluders: &[BOX],
+    queries: &[BOX],
+    path: &str,
+    options: &PlotOptions,
+) -> Result<(), String> {
+    let mut backend = BitMapBackend::new(path, options.size);
+    let layers = buffer_layers(buffer, occluders, queries, options);
+    draw(
+        &mut backend,
+        buffer.overall_bound_box,
+        &layers,
+        options,
+        &WHITE,
+    )
+}
+
+/// Renders the scene into memory as row-major RGBA pixels, 4 bytes per pixel.
+/// The background is transparent, so the picture can be overlaid on screenshots.
+pub fn render_rgba(
+    buffer: &OcclusionBuffer,
+    occluders: &[BOX],
+    queries: &[BOX],
+    options: &PlotOptions,
+) -> Result<Vec<u8>, String> {
+    let (w, h) = options.size;
+    let layers = buffer_layers(buffer, occluders, queries, options);
+    let render = |background: &RGBColor| -> Result<Vec<u8>, String> {
+        let mut rgb = vec![0u8; w as usize * h as usize * 3];
+        {
+            let mut backend = BitMapBackend::with_buffer(&mut rgb, options.size);
+            draw(
+                &mut backend,
+                buffer.overall_bound_box,
+                &layers,
+                options,
+                background,
+            )?;
+        }
+        Ok(rgb)
+    };
+    // the bitmap backend has no alpha channel, so it is recovered from how much
+    // each pixel changes between black and white backgrounds
+    let on_black = render(&BLACK)?;
+    let on_white = render(&WHITE)?;
+    Ok(on_black
+        .chunks_exact(3)
+        .zip(on_white.chunks_exact(3))
+        .flat_map(|(b, wh)| {
+            let shift: u32 = (0..3).map(|c| wh[c].saturating_sub(b[c]) as u32).sum();
+            let alpha = 255 - (shift + 1) / 3;
+            match alpha {
+                0 => [0; 4],
+                _ => {
+                    let unblend = |c: u8| (c as u32 * 255 / alpha).min(255) as u8;
+                    [unblend(b[0]), unblend(b[1]), unblend(b[2]), alpha as u8]
+                }
+            }
+        })
+        .collect())
+}
+
+/// Draws layers in order over the background with any plotters backend, viewport defaults to bounds
+pub(crate) fn draw<DB: DrawingBackend>(
+    backend: &mut DB,
+    bounds: BOX,
+    layers: &[Layer],
+    options: &PlotOptions,
+    background: &RGBColor,
+) -> Result<(), String> {
+    let size = options.size;
+    let viewport = options.viewport.unwrap_or(bounds);
+    if extent(&viewport, 0) <= 0.0 || extent(&viewport, 1) <= 0.0 {
+        return Err(format!(
+            "Viewport must have positive area, got {viewport:?}"
+        ));
+    }
+    let proj = Projection::fit(&viewport, size);
+
+    backend
+        .draw_rect((0, 0), (size.0 as i32, size.1 as i32), background, true)
+        .map_err(msg)?;
+    for layer in layers {
+        for (b, i) in &layer.boxes {
+            let Some((lo, hi)) = proj.rect(b, size) else {
+                continue;
+            };
+            if let Some(fill) = layer.style.fill.map(RGBAColor::from) {
+                backend.draw_rect(lo, hi, &fill, true).map_err(msg)?;
+            }
+            if let Some(stroke) = layer.style.stroke.map(RGBAColor::from) {
+                backend.draw_rect(lo, hi, &stroke, false).map_err(msg)?;
+                if options.labels {
+                    let text = ("sans-serif", FONT_SIZE).into_font().color(&stroke);
+                    backend.draw_text(&i.to_string(), &text, lo).map_err(msg)?;
+                }
+            }
+        }
+    }
+
+    if options.legend {
+        for (row, layer) in layers.iter().enumerate() {
+            let y = 4 + row as i32 * LEGEND_ROW;
+            let (lo, hi) = ((4, y), (14, y + 10));
+            if let Some(fill) = layer.style.fill.map(RGBAColor::from) {
+                backend.draw_rect(lo, hi, &fill, true).map_err(msg)?;
+            }
+            let color = layer.style.stroke.unwrap_or(Color::rgba(0, 0, 0, 1.0));
+            backend
+                .draw_rect(lo, hi, &RGBAColor::from(color), false)
+                .map_err(msg)?;
+            let text = ("sans-serif", FONT_SIZE).into_font().color(&BLACK);
+            backend.draw_text(layer.name, &text, (18, y)).map_err(msg)?;
+        }
+    }
+    backend.present().map_err(msg)
+}
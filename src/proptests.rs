@@ -0,0 +1,112 @@
+//! Property-based tests for invariants that must hold after every cut_space call
+use crate::*;
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+/// Coordinates drawn from a shared grid (touching edges, shared coordinates),
+/// slightly off the grid, or anywhere in range
+fn coord() -> impl Strategy<Value = f32> {
+    prop_oneof![
+        (-8i32..=16).prop_map(|k| k as f32 / 8.0),
+        (-8i32..=16).prop_map(|k| k as f32 / 8.0 + 1e-6),
+        -1.0f32..2.0,
+    ]
+}
+
+fn span() -> impl Strategy<Value = (f32, f32)> {
+    (coord(), coord())
+        .prop_filter("span should not be empty", |(a, b)| a != b)
+        .prop_map(|(a, b)| (a.min(b), a.max(b)))
+}
+
+fn new_box() -> impl Strategy<Value = BOX> {
+    prop_oneof![
+        (span(), span()).prop_map(|(x, y)| BOX::new([x.0, y.0], [x.1, y.1])),
+        // tiny
+        (coord(), coord()).prop_map(|(x, y)| BOX::new([x, y], [x + 1e-5, y + 1e-5])),
+        // huge, covering most of the layout
+        (span(), span())
+            .prop_map(|(x, y)| BOX::new([x.0 - 5.0, y.0 - 5.0], [x.1 + 5.0, y.1 + 5.0])),
+    ]
+}
+
+/// Disjoint free space made of a random subset of cells of a random grid
+fn layout() -> impl Strategy<Value = Vec<BOX>> {
+    (
+        vec(coord(), 2..6),
+        vec(coord(), 2..6),
+        vec(any::<bool>(), 25),
+    )
+        .prop_map(|(mut xs, mut ys, keep)| {
+            for v in [&mut xs, &mut ys] {
+                v.sort_by(f32::total_cmp);
+                v.dedup();
+            }
+            let mut cells = vec![];
+            for (i, x) in xs.windows(2).enumerate() {
+                for (j, y) in ys.windows(2).enumerate() {
+                    if keep[i * 5 + j] {
+                        cells.push(BOX::new([x[0], y[0]], [x[1], y[1]]));
+                    }
+                }
+            }
+            cells
+        })
+}
+
+fn overlap_area(a: &BOX, b: &BOX) -> f32 {
+    let w = a.hi(0).min(b.hi(0)) - a.lo(0).max(b.lo(0));
+    let h = a.hi(1).min(b.hi(1)) - a.lo(1).max(b.lo(1));
+    w.max(0.0) * h.max(0.0)
+}
+
+fn check_insertions(
+    free: Vec<BOX>,
+    news: Vec<BOX>,
+    strategy: SplitStrategy,
+) -> Result<(), TestCaseError> {
+    let mut ob =
+        OcclusionBuffer::from_free_space(BOX::new([-10.0, -10.0], [10.0, 10.0]), free, strategy);
+
+    for new in news {
+        let before: f32 = ob.free_boxes().map(|b| b.area()).sum();
+        let overlap: f32 = ob.free_boxes().map(|f| overlap_area(f, &new)).sum();
+        if ob.find_intersections(new) == OcclusionStatus::PartiallyVisible {
+            ob.add_last_box();
+        }
+
+        let live: Vec<BOX> = ob.free_boxes().copied().collect();
+        for (i, a) in live.iter().enumerate() {
+            prop_assert!(
+                a.lo(0) < a.hi(0) && a.lo(1) < a.hi(1),
+                "Empty box {:?} stored",
+                a
+            );
+            prop_assert!(!a.overlaps_with(&new), "{:?} intersects {:?}", a, new);
+            for b in &live[i + 1..] {
+                prop_assert!(!a.overlaps_with(b), "{:?} intersects {:?}", a, b);
+            }
+        }
+        let after: f32 = live.iter().map(|b| b.area()).sum();
+        prop_assert!(
+            (before - overlap - after).abs() <= 1e-4 * before.max(1.0),
+            "Free area {} after inserting {:?}, expected {} - {}",
+            after,
+            new,
+            before,
+            overlap
+        );
+    }
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn cut_space_invariants(
+        free in layout(),
+        news in vec(new_box(), 1..8),
+        strategy in proptest::sample::select(SplitStrategy::ALL.to_vec()),
+    ) {
+        check_insertions(free, news, strategy)?;
+    }
+}
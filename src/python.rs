@@ -0,0 +1,810 @@
+//! Python bindings, built with the python feature
+use std::collections::VecDeque;
+
+use pyo3::types::{PyIterator, PyModule};
+use pyo3::*;
+
+use crate::*;
+
+#[pyclass]
+#[derive(Clone)]
+pub struct PyOcclusionBuffer {
+    occl_buf: OcclusionBuffer,
+    // occluders added with a payload, in insertion order
+    occluders: Vec<(BOX, PyObject)>,
+}
+
+impl From<OcclusionBuffer> for PyOcclusionBuffer {
+    fn from(occl_buf: OcclusionBuffer) -> Self {
+        Self {
+            occl_buf,
+            occluders: vec![],
+        }
+    }
+}
+
+/// Axis-aligned box with named coordinates, accepted wherever a ((x0, y0), (x1, y1)) tuple is
+#[pyclass(name = "Box")]
+#[derive(Clone, Copy)]
+pub struct PyBox {
+    #[pyo3(get, set)]
+    pub x0: f32,
+    #[pyo3(get, set)]
+    pub y0: f32,
+    #[pyo3(get, set)]
+    pub x1: f32,
+    #[pyo3(get, set)]
+    pub y1: f32,
+}
+
+/// Box argument given either as Box or as ((x0, y0), (x1, y1))
+pub struct PyBoxArg(BOX);
+
+impl<'source> FromPyObject<'source> for PyBoxArg {
+    fn extract(ob: &'source PyAny) -> PyResult<Self> {
+        if let Ok(b) = ob.extract::<PyBox>() {
+            return Ok(Self(BOX::new([b.x0, b.y0], [b.x1, b.y1])));
+        }
+        let (lo, hi): ([f32; 2], [f32; 2]) = ob.extract()?;
+        Ok(Self(BOX::new(lo, hi)))
+    }
+}
+
+fn py_strategy(strategy: Option<&str>) -> PyResult<SplitStrategy> {
+    match strategy {
+        Some(s) => s.parse().map_err(exceptions::PyValueError::new_err),
+        None => Ok(SplitStrategy::default()),
+    }
+}
+
+/// Occluder box as ((x0, y0), (x1, y1)) with its payload
+type PyOccluder = (([f32; 2], [f32; 2]), PyObject);
+
+fn py_tuples<'a>(boxes: impl Iterator<Item = &'a BOX>) -> Vec<([f32; 2], [f32; 2])> {
+    boxes
+        .map(|b| ([b.lo(0), b.lo(1)], [b.hi(0), b.hi(1)]))
+        .collect()
+}
+
+fn py_array<'a, 'py>(
+    py: Python<'py>,
+    boxes: impl Iterator<Item = &'a BOX>,
+) -> PyResult<&'py numpy::PyArray2<f32>> {
+    let flat: Vec<f32> = boxes
+        .flat_map(|b| [b.lo(0), b.lo(1), b.hi(0), b.hi(1)])
+        .collect();
+    let n = flat.len() / 4;
+    numpy::PyArray1::from_vec(py, flat).reshape([n, 4])
+}
+
+fn py_boxes(v: Vec<PyBoxArg>) -> Vec<BOX> {
+    v.into_iter().map(|b| b.0).collect()
+}
+
+/// Error of rendering methods in builds without the plot feature, they are still defined
+/// so that the stub holds for every build
+#[cfg(not(feature = "plot"))]
+fn no_plot(method: &str) -> PyErr {
+    exceptions::PyNotImplementedError::new_err(format!(
+        "{method} needs aabb_occlusion_culling built with the plot feature"
+    ))
+}
+
+#[cfg(feature = "plot")]
+fn py_plot_options(size: (u32, u32), labels: bool) -> PlotOptions {
+    PlotOptions {
+        size,
+        labels,
+        ..Default::default()
+    }
+}
+
+#[pymethods]
+impl PyBox {
+    #[new]
+    pub fn new(x0: f32, y0: f32, x1: f32, y1: f32) -> Self {
+        Self { x0, y0, x1, y1 }
+    }
+
+    #[getter]
+    pub fn lo(&self) -> [f32; 2] {
+        [self.x0, self.y0]
+    }
+
+    #[getter]
+    pub fn hi(&self) -> [f32; 2] {
+        [self.x1, self.y1]
+    }
+
+    #[getter]
+    pub fn width(&self) -> f32 {
+        self.x1 - self.x0
+    }
+
+    #[getter]
+    pub fn height(&self) -> f32 {
+        self.y1 - self.y0
+    }
+
+    #[getter]
+    pub fn area(&self) -> f32 {
+        self.width() * self.height()
+    }
+
+    /// ((x0, y0), (x1, y1)) form used by the buffer methods
+    pub fn to_tuple(&self) -> ([f32; 2], [f32; 2]) {
+        (self.lo(), self.hi())
+    }
+
+    pub fn __repr__(&self) -> String {
+        format!(
+            "Box({:?}, {:?}, {:?}, {:?})",
+            self.x0, self.y0, self.x1, self.y1
+        )
+    }
+
+    pub fn __richcmp__(&self, other: &PyAny, op: pyclass::CompareOp, py: Python<'_>) -> PyObject {
+        let other = match other.extract::<PyBox>() {
+            Ok(o) => o,
+            Err(_) => return py.NotImplemented(),
+        };
+        let eq = self.to_tuple() == other.to_tuple();
+        match op {
+            pyclass::CompareOp::Eq => eq.into_py(py),
+            pyclass::CompareOp::Ne => (!eq).into_py(py),
+            _ => py.NotImplemented(),
+        }
+    }
+}
+
+#[pymethods]
+impl PyOcclusionBuffer {
+    #[new]
+    #[pyo3(signature = (bot, top, strategy = None))]
+    pub fn new(bot: [f32; 2], top: [f32; 2], strategy: Option<&str>) -> PyResult<Self> {
+        let strategy = py_strategy(strategy)?;
+        Ok(OcclusionBuffer::with_strategy(BOX::safe_new(bot, top), strategy).into())
+    }
+
+    pub fn copy(&self) -> PyOcclusionBuffer {
+        self.clone()
+    }
+
+    pub fn __copy__(&self) -> PyOcclusionBuffer {
+        self.clone()
+    }
+
+    /// Copy with payloads copied by copy.deepcopy. The copy is put into memo first,
+    /// so payloads referring back to the buffer do not recurse.
+    pub fn __deepcopy__(slf: PyRef<'_, Self>, memo: &PyAny) -> PyResult<Py<PyOcclusionBuffer>> {
+        let py = slf.py();
+        let deepcopy = py.import("copy")?.getattr("deepcopy")?;
+        let res = Py::new(py, (*slf).clone())?;
+        memo.set_item(slf.as_ptr() as usize, res.clone_ref(py))?;
+        let payloads = slf
+            .occluders
+            .iter()
+            .map(|(_, p)| Ok(deepcopy.call1((p.as_ref(py), memo))?.into()))
+            .collect::<PyResult<Vec<PyObject>>>()?;
+        for ((_, p), copied) in res.borrow_mut(py).occluders.iter_mut().zip(payloads) {
+            *p = copied;
+        }
+        Ok(res)
+    }
+
+    /// Number of live free space fragments
+    pub fn __len__(&self) -> usize {
+        self.occl_buf.free_boxes().count()
+    }
+
+    pub fn __repr__(&self) -> String {
+        let b = self.occl_buf.bounds();
+        format!(
+            "<PyOcclusionBuffer (({:?}, {:?}), ({:?}, {:?})) strategy='{}' fragments={}>",
+            b.lo(0),
+            b.lo(1),
+            b.hi(0),
+            b.hi(1),
+            self.occl_buf.split_strategy(),
+            self.__len__()
+        )
+    }
+
+    /// Bounds as ((x0, y0), (x1, y1))
+    #[getter]
+    pub fn bounds(&self) -> ([f32; 2], [f32; 2]) {
+        let b = self.occl_buf.bounds();
+        ([b.lo(0), b.lo(1)], [b.hi(0), b.hi(1)])
+    }
+
+    #[getter]
+    pub fn strategy(&self) -> String {
+        self.occl_buf.split_strategy().to_string()
+    }
+
+    /// check if a new box intersects free space
+    pub fn check_a_box(&mut self, new: PyBoxArg) -> bool {
+        match self.occl_buf.check_a_box(new.0) {
+            OcclusionStatus::Occluded => false,
+            OcclusionStatus::PartiallyVisible => true,
+        }
+    }
+
+    /// Adds box that was last passed into check_a_box, remembering payload if given
+    #[pyo3(signature = (payload = None))]
+    pub fn add_last_box(&mut self, payload: Option<PyObject>) {
+        let new = self.occl_buf.pending_box();
+        self.occl_buf.add_last_box();
+        if let (Some(b), Some(p)) = (new, payload) {
+            self.occluders.push((b, p));
+        }
+    }
+
+    /// add multiple boxes into zbuffer while cutting space for each one.
+    /// payloads, if given, are remembered with the boxes that were visible and added
+    #[pyo3(signature = (boxes, payloads = None))]
+    pub fn add_box_set(
+        &mut self,
+        boxes: Vec<PyBoxArg>,
+        payloads: Option<Vec<PyObject>>,
+    ) -> PyResult<()> {
+        let boxes = py_boxes(boxes);
+        match payloads {
+            None => self.occl_buf.add_box_set(boxes),
+            Some(payloads) => {
+                if payloads.len() != boxes.len() {
+                    return Err(exceptions::PyValueError::new_err(format!(
+                        "Got {} boxes but {} payloads",
+                        boxes.len(),
+                        payloads.len()
+                    )));
+                }
+                for (b, p) in boxes.into_iter().zip(payloads) {
+                    if self.occl_buf.cull_box(b) == OcclusionStatus::PartiallyVisible {
+                        self.occluders.push((b, p));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Payloads of occluders sharing area with the box, in insertion order
+    pub fn covering(&self, py: Python<'_>, b: PyBoxArg) -> Vec<PyObject> {
+        payload::covering(&self.occluders, b.0)
+            .map(|(_, p)| p.clone_ref(py))
+            .collect()
+    }
+
+    /// Occluders added with a payload as a list of (((x0, y0), (x1, y1)), payload),
+    /// only those lying entirely within region if it is given
+    #[pyo3(signature = (region = None))]
+    pub fn occluders(&self, py: Python<'_>, region: Option<PyBoxArg>) -> Vec<PyOccluder> {
+        let tuple =
+            |(b, p): &(BOX, PyObject)| (([b.lo(0), b.lo(1)], [b.hi(0), b.hi(1)]), p.clone_ref(py));
+        match region {
+            Some(r) => payload::enclosed_by(&self.occluders, r.0)
+                .map(tuple)
+                .collect(),
+            None => self.occluders.iter().map(tuple).collect(),
+        }
+    }
+
+    /// Lazily culls boxes from any iterable, in order, yielding (index, visible) per box.
+    /// Visible boxes are added as with add_box_set. Boxes are pulled chunk_size at a time and
+    /// each chunk is processed with the GIL released, the buffer is borrowed meanwhile.
+    #[pyo3(signature = (boxes, chunk_size = 256))]
+    pub fn cull_iter(
+        slf: PyRef<'_, Self>,
+        boxes: &PyAny,
+        chunk_size: usize,
+    ) -> PyResult<PyCullIter> {
+        if chunk_size == 0 {
+            return Err(exceptions::PyValueError::new_err(
+                "chunk_size must be positive",
+            ));
+        }
+        Ok(PyCullIter {
+            boxes: PyIterator::from_object(boxes.py(), boxes)?.into(),
+            buffer: slf.into(),
+            chunk_size,
+            next_index: 0,
+            chunk: Vec::new(),
+            ready: VecDeque::new(),
+            error: None,
+            finished: false,
+        })
+    }
+
+    /// Buffer health statistics as a dict
+    pub fn stats(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let stats = self.occl_buf.stats();
+        let d = types::PyDict::new(py);
+        d.set_item("fragments", stats.fragments)?;
+        d.set_item("dead_slots", stats.dead_slots)?;
+        d.set_item("free_area", stats.free_area)?;
+        d.set_item("largest_fragment", stats.largest_fragment)?;
+        d.set_item("cuts", stats.cuts)?;
+        d.set_item("next_index", stats.next_index)?;
+        if let Some(c) = stats.counters {
+            d.set_item("queries", c.queries)?;
+            d.set_item("visible", c.visible)?;
+            d.set_item("occluded", c.occluded)?;
+        }
+        Ok(d.into())
+    }
+
+    /// Enables (and resets) or disables counting of queries by outcome
+    #[pyo3(signature = (enable = true))]
+    pub fn enable_counters(&mut self, enable: bool) {
+        self.occl_buf.enable_counters(enable);
+    }
+
+    /// Restores full free space, keeping allocated memory
+    pub fn reset(&mut self) {
+        self.occl_buf.reset();
+        self.occluders.clear();
+    }
+
+    /// Changes bounds keeping occluders in place, new margins become free space.
+    /// Occluders with payloads are clipped as in translate
+    pub fn set_bounds(&mut self, bot: [f32; 2], top: [f32; 2]) {
+        self.occl_buf.set_bounds(BOX::safe_new(bot, top));
+        payload::clip_boxes(&mut self.occluders, self.occl_buf.bounds());
+    }
+
+    /// Moves occluders by offset, bounds stay in place.
+    /// Occluders with payloads are clipped to bounds, those leaving them are dropped
+    pub fn translate(&mut self, offset: [f32; 2]) {
+        self.occl_buf.translate(offset);
+        payload::translate_boxes(&mut self.occluders, self.occl_buf.bounds(), offset);
+    }
+
+    /// Scales occluders around center by positive factors, bounds stay in place.
+    /// Occluders with payloads are clipped as in translate
+    pub fn scale(&mut self, center: [f32; 2], factors: [f32; 2]) -> PyResult<()> {
+        if factors[0] <= 0.0 || factors[1] <= 0.0 {
+            return Err(exceptions::PyValueError::new_err(format!(
+                "Scale factors must be positive, got {factors:?}"
+            )));
+        }
+        self.occl_buf.scale(center, factors);
+        payload::scale_boxes(&mut self.occluders, self.occl_buf.bounds(), center, factors);
+        Ok(())
+    }
+
+    /// Writes free space, occluders and query boxes into an svg file, fitted to buffer bounds
+    #[cfg(feature = "plot")]
+    #[pyo3(signature = (path, occluders = vec![], queries = vec![], size = (512, 512), labels = false))]
+    pub fn export_svg(
+        &self,
+        path: &str,
+        occluders: Vec<PyBoxArg>,
+        queries: Vec<PyBoxArg>,
+        size: (u32, u32),
+        labels: bool,
+    ) -> PyResult<()> {
+        export_svg(
+            &self.occl_buf,
+            &py_boxes(occluders),
+            &py_boxes(queries),
+            path,
+            &py_plot_options(size, labels),
+        )
+        .map_err(exceptions::PyIOError::new_err)
+    }
+
+    /// Same as export_svg, but writes a png file
+    #[cfg(feature = "plot")]
+    #[pyo3(signature = (path, occluders = vec![], queries = vec![], size = (512, 512), labels = false))]
+    pub fn export_png(
+        &self,
+        path: &str,
+        occluders: Vec<PyBoxArg>,
+        queries: Vec<PyBoxArg>,
+        size: (u32, u32),
+        labels: bool,
+    ) -> PyResult<()> {
+        export_png(
+            &self.occl_buf,
+            &py_boxes(occluders),
+            &py_boxes(queries),
+            path,
+            &py_plot_options(size, labels),
+        )
+        .map_err(exceptions::PyIOError::new_err)
+    }
+
+    /// Renders the scene into a numpy array of shape (height, width, 4), dtype uint8, background is transparent
+    #[cfg(feature = "plot")]
+    #[pyo3(signature = (occluders = vec![], queries = vec![], size = (512, 512), labels = false))]
+    pub fn render_rgba<'py>(
+        &self,
+        py: Python<'py>,
+        occluders: Vec<PyBoxArg>,
+        queries: Vec<PyBoxArg>,
+        size: (u32, u32),
+        labels: bool,
+    ) -> PyResult<&'py numpy::PyArray3<u8>> {
+        let pixels = render_rgba(
+            &self.occl_buf,
+            &py_boxes(occluders),
+            &py_boxes(queries),
+            &py_plot_options(size, labels),
+        )
+        .map_err(exceptions::PyRuntimeError::new_err)?;
+        numpy::PyArray1::from_vec(py, pixels).reshape([size.1 as usize, size.0 as usize, 4])
+    }
+
+    /// Starts recording queries and insertions for export_trace
+    pub fn start_trace(&mut self) {
+        self.occl_buf.start_trace();
+    }
+
+    /// Stops recording and writes the trace as html page with a slider (for .html paths)
+    /// or as animated svg, showing each frame for frame_seconds
+    #[cfg(feature = "plot")]
+    #[pyo3(signature = (path, frame_seconds = 0.5, size = (512, 512), labels = false))]
+    pub fn export_trace(
+        &mut self,
+        path: &str,
+        frame_seconds: f32,
+        size: (u32, u32),
+        labels: bool,
+    ) -> PyResult<()> {
+        let trace = self
+            .occl_buf
+            .take_trace()
+            .ok_or_else(|| exceptions::PyRuntimeError::new_err("start_trace was not called"))?;
+        let options = py_plot_options(size, labels);
+        let doc = match path.ends_with(".html") {
+            true => trace.to_html(&options),
+            false => trace.to_animated_svg(&options, frame_seconds),
+        }
+        .map_err(exceptions::PyRuntimeError::new_err)?;
+        std::fs::write(path, doc).map_err(|e| exceptions::PyIOError::new_err(e.to_string()))
+    }
+
+    /// Raises NotImplementedError, the module was built without the plot feature
+    #[cfg(not(feature = "plot"))]
+    #[pyo3(signature = (*_args, **_kwargs))]
+    pub fn export_svg(
+        &self,
+        _args: &types::PyTuple,
+        _kwargs: Option<&types::PyDict>,
+    ) -> PyResult<()> {
+        Err(no_plot("export_svg"))
+    }
+
+    /// Raises NotImplementedError, the module was built without the plot feature
+    #[cfg(not(feature = "plot"))]
+    #[pyo3(signature = (*_args, **_kwargs))]
+    pub fn export_png(
+        &self,
+        _args: &types::PyTuple,
+        _kwargs: Option<&types::PyDict>,
+    ) -> PyResult<()> {
+        Err(no_plot("export_png"))
+    }
+
+    /// Raises NotImplementedError, the module was built without the plot feature
+    #[cfg(not(feature = "plot"))]
+    #[pyo3(signature = (*_args, **_kwargs))]
+    pub fn render_rgba(
+        &self,
+        _args: &types::PyTuple,
+        _kwargs: Option<&types::PyDict>,
+    ) -> PyResult<()> {
+        Err(no_plot("render_rgba"))
+    }
+
+    /// Raises NotImplementedError, the module was built without the plot feature
+    #[cfg(not(feature = "plot"))]
+    #[pyo3(signature = (*_args, **_kwargs))]
+    pub fn export_trace(
+        &self,
+        _args: &types::PyTuple,
+        _kwargs: Option<&types::PyDict>,
+    ) -> PyResult<()> {
+        Err(no_plot("export_trace"))
+    }
+
+    /// Starts logging operations on the buffer, see recording and replay
+    pub fn start_recording(&mut self) {
+        self.occl_buf.start_recording();
+    }
+
+    /// Text log of operations since start_recording, None if not recording
+    pub fn recording(&self) -> Option<String> {
+        self.occl_buf.recording().map(|log| log.to_string())
+    }
+
+    /// Rebuilds buffer by running a text log produced by recording
+    #[staticmethod]
+    pub fn replay(log: &str) -> PyResult<Self> {
+        let log: OpLog = log.parse().map_err(exceptions::PyValueError::new_err)?;
+        let occl_buf = log.replay().map_err(exceptions::PyValueError::new_err)?;
+        Ok(occl_buf.into())
+    }
+
+    /// Live free space fragments as a list of ((x0, y0), (x1, y1))
+    pub fn free_space(&self) -> Vec<([f32; 2], [f32; 2])> {
+        py_tuples(self.occl_buf.free_boxes())
+    }
+
+    /// Live free space fragments as float32 array of shape (N, 4): x0, y0, x1, y1
+    pub fn free_space_array<'py>(&self, py: Python<'py>) -> PyResult<&'py numpy::PyArray2<f32>> {
+        py_array(py, self.occl_buf.free_boxes())
+    }
+
+    /// Occupied part of the bounds as a list of ((x0, y0), (x1, y1)), boxes do not overlap
+    pub fn occupied(&self) -> Vec<([f32; 2], [f32; 2])> {
+        py_tuples(self.occl_buf.occupied_boxes().iter())
+    }
+
+    /// Occupied part of the bounds as float32 array of shape (N, 4): x0, y0, x1, y1
+    pub fn occupied_array<'py>(&self, py: Python<'py>) -> PyResult<&'py numpy::PyArray2<f32>> {
+        py_array(py, self.occl_buf.occupied_boxes().iter())
+    }
+
+    /// Free space as GeoJSON FeatureCollection, optionally with the occupied region
+    #[pyo3(signature = (occupied = false))]
+    pub fn to_geojson(&self, occupied: bool) -> String {
+        free_space_geojson(&self.occl_buf, occupied)
+    }
+
+    /// Free space as WKT MultiPolygon
+    pub fn to_wkt(&self) -> String {
+        free_space_wkt(&self.occl_buf)
+    }
+
+    /// Occupied part of the bounds as WKT MultiPolygon
+    pub fn occupied_wkt(&self) -> String {
+        occupied_wkt(&self.occl_buf)
+    }
+
+    /// Builds a buffer from GeoJSON rectangles: free space, plus features with kind "occupied" as occluders
+    #[staticmethod]
+    #[pyo3(signature = (text, strategy = None))]
+    pub fn from_geojson(text: &str, strategy: Option<&str>) -> PyResult<Self> {
+        let strategy = py_strategy(strategy)?;
+        let occl_buf =
+            buffer_from_geojson(text, strategy).map_err(exceptions::PyValueError::new_err)?;
+        Ok(occl_buf.into())
+    }
+
+    /// Builds a buffer whose free space is a WKT (Multi)Polygon of rectangles
+    #[staticmethod]
+    #[pyo3(signature = (text, bounds = None, strategy = None))]
+    pub fn from_wkt(
+        text: &str,
+        bounds: Option<PyBoxArg>,
+        strategy: Option<&str>,
+    ) -> PyResult<Self> {
+        let strategy = py_strategy(strategy)?;
+        let bounds = bounds.map(|b| b.0);
+        let occl_buf =
+            buffer_from_wkt(text, bounds, strategy).map_err(exceptions::PyValueError::new_err)?;
+        Ok(occl_buf.into())
+    }
+
+    /// Removes deleted fragments from free space storage
+    pub fn compact(&mut self) {
+        self.occl_buf.compact();
+    }
+
+    /// Sets fraction of deleted fragments that triggers compaction, None disables it
+    pub fn set_compaction_threshold(&mut self, threshold: Option<f32>) {
+        self.occl_buf.set_compaction_threshold(threshold);
+    }
+}
+
+/// Iterator returned by PyOcclusionBuffer.cull_iter
+#[pyclass(name = "CullIterator")]
+pub struct PyCullIter {
+    buffer: Py<PyOcclusionBuffer>,
+    boxes: Py<PyIterator>,
+    chunk_size: usize,
+    // index of the next box pulled from boxes
+    next_index: usize,
+    // boxes pulled but not culled yet, kept when the buffer could not be borrowed
+    chunk: Vec<BOX>,
+    // results of the current chunk not yet yielded
+    ready: VecDeque<(usize, bool)>,
+    // error from boxes, raised once the boxes pulled before it are yielded
+    error: Option<PyErr>,
+    // boxes is exhausted or has raised
+    finished: bool,
+}
+
+#[pymethods]
+impl PyCullIter {
+    pub fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    /// Boxes pulled before a failing one are culled and yielded first, then the error is raised
+    /// and the iterator is finished. If the buffer is borrowed, the pulled chunk is kept for the next call.
+    pub fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<(usize, bool)>> {
+        if self.ready.is_empty() && self.chunk.is_empty() && !self.finished {
+            let mut boxes = self.boxes.as_ref(py);
+            while self.chunk.len() < self.chunk_size {
+                match boxes.next().map(|b| b?.extract::<PyBoxArg>()) {
+                    Some(Ok(b)) => self.chunk.push(b.0),
+                    Some(Err(e)) => {
+                        self.error = Some(e);
+                        self.finished = true;
+                        break;
+                    }
+                    None => {
+                        self.finished = true;
+                        break;
+                    }
+                }
+            }
+        }
+        if self.ready.is_empty() && !self.chunk.is_empty() {
+            let mut buffer = self.buffer.try_borrow_mut(py)?;
+            let occl_buf = &mut buffer.occl_buf;
+            let chunk = std::mem::take(&mut self.chunk);
+            let visible: Vec<bool> = py.allow_threads(|| {
+                chunk
+                    .into_iter()
+                    .map(|b| occl_buf.cull_box(b) == OcclusionStatus::PartiallyVisible)
+                    .collect()
+            });
+            let first = self.next_index;
+            self.next_index += visible.len();
+            self.ready.extend((first..).zip(visible));
+        }
+        match (self.ready.pop_front(), self.error.take()) {
+            (None, Some(e)) => Err(e),
+            (res, e) => {
+                self.error = e;
+                Ok(res)
+            }
+        }
+    }
+}
+
+/// Scene occluder as (id, bot, top, depth, role)
+type PySceneOccluder = (u64, [f32; 2], [f32; 2], f32, &'static str);
+
+/// Scene file contents: bounds, occluders and query boxes
+#[pyclass(name = "Scene")]
+#[derive(Clone)]
+pub struct PyScene {
+    scene: Scene,
+}
+
+#[pymethods]
+impl PyScene {
+    #[new]
+    pub fn new(bot: [f32; 2], top: [f32; 2]) -> Self {
+        Self {
+            scene: Scene::new(BOX::safe_new(bot, top)),
+        }
+    }
+
+    /// Reads .json files as JSON and anything else as binary scene
+    #[staticmethod]
+    pub fn load(path: &str) -> PyResult<Self> {
+        let scene = Scene::load(path).map_err(exceptions::PyIOError::new_err)?;
+        Ok(Self { scene })
+    }
+
+    pub fn save(&self, path: &str) -> PyResult<()> {
+        self.scene
+            .save(path)
+            .map_err(exceptions::PyIOError::new_err)
+    }
+
+    #[staticmethod]
+    pub fn from_json(text: &str) -> PyResult<Self> {
+        let scene = Scene::from_json(text).map_err(exceptions::PyValueError::new_err)?;
+        Ok(Self { scene })
+    }
+
+    pub fn to_json(&self) -> String {
+        self.scene.to_json()
+    }
+
+    #[getter]
+    pub fn version(&self) -> u32 {
+        self.scene.version
+    }
+
+    #[getter]
+    pub fn bounds(&self) -> ([f32; 2], [f32; 2]) {
+        (self.scene.bounds[0], self.scene.bounds[1])
+    }
+
+    /// role is "object" (checked, added when visible) or "occluder" (always added, not reported)
+    #[pyo3(signature = (id, bot, top, depth = 0.0, role = "object"))]
+    pub fn add_occluder(
+        &mut self,
+        id: u64,
+        bot: [f32; 2],
+        top: [f32; 2],
+        depth: f32,
+        role: &str,
+    ) -> PyResult<()> {
+        let role = match role {
+            "object" => Role::Object,
+            "occluder" => Role::Occluder,
+            other => {
+                return Err(exceptions::PyValueError::new_err(format!(
+                    "Unknown role {other:?}"
+                )))
+            }
+        };
+        self.scene.occluders.push(SceneOccluder {
+            id,
+            lo: bot,
+            hi: top,
+            depth,
+            role,
+        });
+        Ok(())
+    }
+
+    pub fn add_query(&mut self, id: u64, bot: [f32; 2], top: [f32; 2]) {
+        self.scene.queries.push(SceneQuery {
+            id,
+            lo: bot,
+            hi: top,
+        });
+    }
+
+    /// List of (id, bot, top, depth, role)
+    pub fn occluders(&self) -> Vec<PySceneOccluder> {
+        self.scene
+            .occluders
+            .iter()
+            .map(|o| {
+                let role = match o.role {
+                    Role::Object => "object",
+                    Role::Occluder => "occluder",
+                };
+                (o.id, o.lo, o.hi, o.depth, role)
+            })
+            .collect()
+    }
+
+    /// List of (id, bot, top)
+    pub fn queries(&self) -> Vec<(u64, [f32; 2], [f32; 2])> {
+        self.scene
+            .queries
+            .iter()
+            .map(|q| (q.id, q.lo, q.hi))
+            .collect()
+    }
+
+    /// Runs the scene through a new buffer, returns the buffer and dict of id -> visible
+    #[pyo3(signature = (strategy = None))]
+    pub fn run(
+        &self,
+        py: Python<'_>,
+        strategy: Option<&str>,
+    ) -> PyResult<(PyOcclusionBuffer, PyObject)> {
+        let strategy = py_strategy(strategy)?;
+        let (occl_buf, visibility) = self
+            .scene
+            .run(strategy)
+            .map_err(exceptions::PyValueError::new_err)?;
+        let d = types::PyDict::new(py);
+        for (id, status) in visibility {
+            d.set_item(id, status == OcclusionStatus::PartiallyVisible)?;
+        }
+        Ok((occl_buf.into(), d.into()))
+    }
+}
+
+#[pymodule]
+fn aabb_occlusion_culling(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_class::<PyBox>()?;
+    m.add_class::<PyOcclusionBuffer>()?;
+    m.add_class::<PyCullIter>()?;
+    m.add_class::<PyScene>()?;
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use crate::box_cutting::*;
+use box_intersect_ze::boxes::BBox;
+
+/// Reference occlusion buffer that stores occupancy as a bitmap over bounds.
+/// A cell is covered by a box if the cell center lies strictly inside the box,
+/// so answers converge to the exact ones as resolution grows.
+#[derive(Clone, Debug)]
+pub struct RasterBuffer {
+    bounds: BOX,
+    width: usize,
+    height: usize,
+    occupied: Vec<bool>,
+}
+
+impl RasterBuffer {
+    pub fn new(bounds: BOX, width: usize, height: usize) -> Self {
+        assert!(
+            width > 0 && height > 0,
+            "Raster should have at least one cell"
+        );
+        RasterBuffer {
+            bounds,
+            width,
+            height,
+            occupied: vec![false; width * height],
+        }
+    }
+
+    pub fn bounds(&self) -> BOX {
+        self.bounds
+    }
+
+    /// Area covered by a single cell
+    pub fn cell_area(&self) -> f32 {
+        self.bounds.area() / (self.width * self.height) as f32
+    }
+
+    /// Checks if any cell within the box is still free
+    pub fn is_visible(&self, b: &BOX) -> bool {
+        let (xs, mut ys) = self.cells_in(b);
+        ys.any(|y| {
+            self.occupied[y * self.width + xs.start..y * self.width + xs.end].contains(&false)
+        })
+    }
+
+    /// Marks all cells within the box as occupied
+    pub fn occlude(&mut self, b: BOX) {
+        let (xs, ys) = self.cells_in(&b);
+        for y in ys {
+            self.occupied[y * self.width + xs.start..y * self.width + xs.end].fill(true);
+        }
+    }
+
+    /// Marks all cells as free
+    pub fn clear(&mut self) {
+        self.occupied.fill(false);
+    }
+
+    /// Total area of occupied cells
+    pub fn covered_area(&self) -> f32 {
+        self.occupied.iter().filter(|&&o| o).count() as f32 * self.cell_area()
+    }
+
+    /// Free space as horizontal runs of free cells, one box per run
+    pub fn free_runs(&self) -> impl Iterator<Item = BOX> + '_ {
+        (0..self.height).flat_map(move |y| {
+            let row = &self.occupied[y * self.width..(y + 1) * self.width];
+            let mut runs = vec![];
+            let mut start = None;
+            for (x, &occ) in row.iter().chain(std::iter::once(&true)).enumerate() {
+                match (start, occ) {
+                    (None, false) => start = Some(x),
+                    (Some(s), true) => {
+                        runs.push(self.cell_box(s..x, y));
+                        start = None;
+                    }
+                    _ => {}
+                }
+            }
+            runs
+        })
+    }
+
+    /// Box spanning cells xs in row y
+    fn cell_box(&self, xs: std::ops::Range<usize>, y: usize) -> BOX {
+        let (dx, dy) = self.cell_size();
+        let (x0, y0) = (self.bounds.lo(0) as f64, self.bounds.lo(1) as f64);
+        BOX::new(
+            [
+                (x0 + xs.start as f64 * dx) as f32,
+                (y0 + y as f64 * dy) as f32,
+            ],
+            [
+                (x0 + xs.end as f64 * dx) as f32,
+                (y0 + (y + 1) as f64 * dy) as f32,
+            ],
+        )
+    }
+
+    fn cell_size(&self) -> (f64, f64) {
+        (
+            (self.bounds.hi(0) as f64 - self.bounds.lo(0) as f64) / self.width as f64,
+            (self.bounds.hi(1) as f64 - self.bounds.lo(1) as f64) / self.height as f64,
+        )
+    }
+
+    /// Ranges of cells whose centers lie strictly inside the box
+    fn cells_in(&self, b: &BOX) -> (std::ops::Range<usize>, std::ops::Range<usize>) {
+        let (dx, dy) = self.cell_size();
+        let range = |lo: f32, hi: f32, origin: f32, step: f64, n: usize| {
+            // center of cell i is at origin + (i + 0.5) * step
+            let first = ((lo as f64 - origin as f64) / step - 0.5).floor() + 1.0;
+            let end = ((hi as f64 - origin as f64) / step - 0.5).ceil();
+            let first = first.clamp(0.0, n as f64) as usize;
+            let end = end.clamp(0.0, n as f64) as usize;
+            first..end.max(first)
+        };
+        (
+            range(b.lo(0), b.hi(0), self.bounds.lo(0), dx, self.width),
+            range(b.lo(1), b.hi(1), self.bounds.lo(1), dy, self.height),
+        )
+    }
+}
@@ -0,0 +1,290 @@
+//! Versioned interchange format for buffer inputs.
+//!
+//! JSON form:
+//! ```json
+//! {
+//!   "version": 1,
+//!   "bounds": [[0, 0], [10, 10]],
+//!   "occluders": [{"id": 1, "lo": [1, 1], "hi": [4, 4], "depth": 0.5, "role": "object"}],
+//!   "queries": [{"id": 100, "lo": [2, 2], "hi": [3, 3]}]
+//! }
+//! ```
+//! `depth` defaults to 0 and `role` to "object". JSON needs the serde feature. Binary form is little-endian:
+//! magic `AOCS`, u32 version, bounds as 4 f32 (lo x, lo y, hi x, hi y),
+//! u32 occluder count, per occluder u64 id, 4 f32 box, f32 depth, u8 role (0 object, 1 occluder),
+//! u32 query count, per query u64 id and 4 f32 box.
+use box_intersect_ze::boxes::BBox;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::box_cutting::*;
+use crate::{OcclusionBuffer, OcclusionQuery, OcclusionStatus};
+
+/// Latest version of the scene format, readers accept this and older versions
+pub const SCENE_VERSION: u32 = 1;
+const MAGIC: &[u8; 4] = b"AOCS";
+
+/// How an occluder takes part in a scene run
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Role {
+    /// Checked for visibility, added to the buffer when visible
+    #[default]
+    Object,
+    /// Added to the buffer without being reported, e.g. static foreground
+    Occluder,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SceneOccluder {
+    pub id: u64,
+    pub lo: [f32; 2],
+    pub hi: [f32; 2],
+    /// Distance from the viewer, occluders are processed front to back
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub depth: f32,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub role: Role,
+}
+
+/// Box checked against the final state of the buffer
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SceneQuery {
+    pub id: u64,
+    pub lo: [f32; 2],
+    pub hi: [f32; 2],
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Scene {
+    pub version: u32,
+    pub bounds: [[f32; 2]; 2],
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub occluders: Vec<SceneOccluder>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub queries: Vec<SceneQuery>,
+}
+
+impl SceneOccluder {
+    pub fn bbox(&self) -> BOX {
+        BOX::new(self.lo, self.hi)
+    }
+}
+
+impl SceneQuery {
+    pub fn bbox(&self) -> BOX {
+        BOX::new(self.lo, self.hi)
+    }
+}
+
+impl Scene {
+    pub fn new(bounds: BOX) -> Self {
+        Self {
+            version: SCENE_VERSION,
+            bounds: [[bounds.lo(0), bounds.lo(1)], [bounds.hi(0), bounds.hi(1)]],
+            occluders: vec![],
+            queries: vec![],
+        }
+    }
+
+    pub fn bbox(&self) -> BOX {
+        BOX::new(self.bounds[0], self.bounds[1])
+    }
+
+    /// Occluders in processing order: by depth, file order among equal depths
+    pub fn ordered_occluders(&self) -> Vec<SceneOccluder> {
+        let mut res = self.occluders.clone();
+        res.sort_by(|a, b| a.depth.total_cmp(&b.depth));
+        res
+    }
+
+    /// Runs occluders front to back through a new buffer, adding them when visible, then checks
+    /// queries against it. Boxes have to lie within the bounds and may touch or cover them,
+    /// so they go through OcclusionQuery rather than the strictly checked cull_box/check_a_box.
+    /// Returns the buffer and visibility of objects (in processing order) followed by queries.
+    pub fn run(
+        &self,
+        strategy: SplitStrategy,
+    ) -> Result<(OcclusionBuffer, Vec<(u64, OcclusionStatus)>), String> {
+        let bounds = self.bbox();
+        let outside = |what: &str, id: u64| {
+            Err(format!(
+                "{what} {id} is outside of scene bounds {:?}",
+                self.bounds
+            ))
+        };
+        if let Some(o) = self.occluders.iter().find(|o| !bounds.encloses(&o.bbox())) {
+            return outside("Occluder", o.id);
+        }
+        if let Some(q) = self.queries.iter().find(|q| !bounds.encloses(&q.bbox())) {
+            return outside("Query", q.id);
+        }
+
+        let mut buffer = OcclusionBuffer::with_strategy(bounds, strategy);
+        let mut visibility = vec![];
+        for o in self.ordered_occluders() {
+            let status = OcclusionQuery::query(&mut buffer, &o.bbox());
+            if status == OcclusionStatus::PartiallyVisible {
+                buffer.add_last_box();
+            }
+            if o.role == Role::Object {
+                visibility.push((o.id, status));
+            }
+        }
+        for q in &self.queries {
+            visibility.push((q.id, OcclusionQuery::query(&mut buffer, &q.bbox())));
+        }
+        Ok((buffer, visibility))
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        let scene: Scene = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        scene.check_version()?;
+        Ok(scene)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Scene is always serializable")
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+        let f32s = |out: &mut Vec<u8>, v: &[f32]| {
+            for x in v {
+                out.extend(x.to_le_bytes());
+            }
+        };
+        out.extend(self.version.to_le_bytes());
+        f32s(
+            &mut out,
+            &[
+                self.bounds[0][0],
+                self.bounds[0][1],
+                self.bounds[1][0],
+                self.bounds[1][1],
+            ],
+        );
+        out.extend((self.occluders.len() as u32).to_le_bytes());
+        for o in &self.occluders {
+            out.extend(o.id.to_le_bytes());
+            f32s(&mut out, &[o.lo[0], o.lo[1], o.hi[0], o.hi[1], o.depth]);
+            out.push(match o.role {
+                Role::Object => 0,
+                Role::Occluder => 1,
+            });
+        }
+        out.extend((self.queries.len() as u32).to_le_bytes());
+        for q in &self.queries {
+            out.extend(q.id.to_le_bytes());
+            f32s(&mut out, &[q.lo[0], q.lo[1], q.hi[0], q.hi[1]]);
+        }
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut r = Reader { bytes, pos: 0 };
+        if r.take(4)? != MAGIC {
+            return Err("Not a binary scene file".to_owned());
+        }
+        let version = r.u32()?;
+        let bounds = [[r.f32()?, r.f32()?], [r.f32()?, r.f32()?]];
+        let mut scene = Scene {
+            version,
+            bounds,
+            occluders: vec![],
+            queries: vec![],
+        };
+        scene.check_version()?;
+        for _ in 0..r.u32()? {
+            scene.occluders.push(SceneOccluder {
+                id: r.u64()?,
+                lo: [r.f32()?, r.f32()?],
+                hi: [r.f32()?, r.f32()?],
+                depth: r.f32()?,
+                role: match r.take(1)?[0] {
+                    0 => Role::Object,
+                    1 => Role::Occluder,
+                    x => return Err(format!("Unknown role {x} at byte {}", r.pos - 1)),
+                },
+            });
+        }
+        for _ in 0..r.u32()? {
+            scene.queries.push(SceneQuery {
+                id: r.u64()?,
+                lo: [r.f32()?, r.f32()?],
+                hi: [r.f32()?, r.f32()?],
+            });
+        }
+        if r.pos != bytes.len() {
+            return Err(format!(
+                "{} unexpected bytes after the scene at byte {}",
+                bytes.len() - r.pos,
+                r.pos
+            ));
+        }
+        Ok(scene)
+    }
+
+    /// Reads a scene, .json files as JSON and anything else as binary
+    #[cfg(feature = "serde")]
+    pub fn load(path: &str) -> Result<Self, String> {
+        let err = |e: std::io::Error| format!("{path}: {e}");
+        match path.ends_with(".json") {
+            true => Self::from_json(&std::fs::read_to_string(path).map_err(err)?),
+            false => Self::from_bytes(&std::fs::read(path).map_err(err)?),
+        }
+    }
+
+    /// Writes a scene, to .json files as JSON and to anything else as binary
+    #[cfg(feature = "serde")]
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let res = match path.ends_with(".json") {
+            true => std::fs::write(path, self.to_json()),
+            false => std::fs::write(path, self.to_bytes()),
+        };
+        res.map_err(|e| format!("{path}: {e}"))
+    }
+
+    fn check_version(&self) -> Result<(), String> {
+        match self.version {
+            1..=SCENE_VERSION => Ok(()),
+            v => Err(format!(
+                "Unsupported scene version {v}, supported are 1 to {SCENE_VERSION}"
+            )),
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let res = self
+            .bytes
+            .get(self.pos..self.pos + n)
+            .ok_or_else(|| format!("Scene truncated at byte {}", self.pos))?;
+        self.pos += n;
+        Ok(res)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn f32(&mut self) -> Result<f32, String> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
@@ -0,0 +1,28 @@
+/// Snapshot of buffer health, as returned by OcclusionBuffer::stats
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BufferStats {
+    /// Live fragments of free space
+    pub fragments: usize,
+    /// Deleted fragments whose slots in free_space wait for reuse
+    pub dead_slots: usize,
+    /// Total area of free space
+    pub free_area: f32,
+    /// Area of the largest fragment of free space
+    pub largest_fragment: f32,
+    /// Number of boxes that were cut out of free space
+    pub cuts: usize,
+    /// Index that will be given to the next fragment
+    pub next_index: usize,
+    /// Query counters, if enabled with OcclusionBuffer::enable_counters
+    pub counters: Option<QueryCounters>,
+}
+
+/// Counts visibility checks (check_a_box, cull_box and OcclusionQuery::query) by outcome
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueryCounters {
+    pub queries: u64,
+    /// Checks that found the box partially visible
+    pub visible: u64,
+    /// Checks that found the box occluded
+    pub occluded: u64,
+}
@@ -0,0 +1,175 @@
+#[cfg(feature = "plot")]
+use std::fmt::Write;
+
+use crate::box_cutting::*;
+#[cfg(feature = "plot")]
+use crate::plot::{layers_svg, BoxStyle, Color, Layer, PlotOptions};
+use crate::OcclusionStatus;
+
+#[cfg(feature = "plot")]
+const INTERSECTED: BoxStyle = BoxStyle {
+    fill: None,
+    stroke: Some(Color::rgba(255, 140, 0, 1.0)),
+};
+#[cfg(feature = "plot")]
+const PRODUCED: BoxStyle = BoxStyle {
+    fill: Some(Color::rgba(200, 230, 201, 1.0)),
+    stroke: Some(Color::rgba(0, 128, 0, 1.0)),
+};
+
+/// One recorded operation on the buffer
+#[derive(Clone, Debug)]
+pub enum TraceStep {
+    /// check_a_box call and its result
+    Query { query: BOX, status: OcclusionStatus },
+    /// add_last_box call: fragments intersected by the new box and fragments that replaced them
+    Insert {
+        new: BOX,
+        intersected: Vec<(BOX, usize)>,
+        produced: Vec<(BOX, usize)>,
+    },
+}
+
+/// Step together with free space right after it
+#[derive(Clone, Debug)]
+pub struct TraceFrame {
+    pub step: TraceStep,
+    pub free_space: Vec<(BOX, usize)>,
+}
+
+/// Recording of an insertion session, see OcclusionBuffer::start_trace
+#[derive(Clone, Debug)]
+pub struct Trace {
+    pub bounds: BOX,
+    pub frames: Vec<TraceFrame>,
+}
+
+impl Trace {
+    pub fn new(bounds: BOX) -> Self {
+        Self {
+            bounds,
+            frames: vec![],
+        }
+    }
+}
+
+/// Rendering of recorded frames
+#[cfg(feature = "plot")]
+impl Trace {
+    /// Svg picture of a single frame: free space, query or new box, intersected and produced fragments
+    pub fn frame_svg(&self, frame: usize, options: &PlotOptions) -> Result<String, String> {
+        let f = self
+            .frames
+            .get(frame)
+            .ok_or_else(|| format!("No frame {frame}, trace has {}", self.frames.len()))?;
+        let mut layers = vec![Layer {
+            name: "free space",
+            style: options.free_space,
+            boxes: f.free_space.clone(),
+        }];
+        match &f.step {
+            TraceStep::Query { query, .. } => layers.push(Layer {
+                name: "query",
+                style: options.queries,
+                boxes: vec![(*query, frame)],
+            }),
+            TraceStep::Insert {
+                new,
+                intersected,
+                produced,
+            } => {
+                layers.push(Layer {
+                    name: "produced",
+                    style: PRODUCED,
+                    boxes: produced.clone(),
+                });
+                layers.push(Layer {
+                    name: "intersected",
+                    style: INTERSECTED,
+                    boxes: intersected.clone(),
+                });
+                layers.push(Layer {
+                    name: "new box",
+                    style: options.occluders,
+                    boxes: vec![(*new, frame)],
+                });
+            }
+        }
+        layers_svg(self.bounds, &layers, options)
+    }
+
+    /// Single svg cycling through all frames, each shown for frame_seconds
+    pub fn to_animated_svg(
+        &self,
+        options: &PlotOptions,
+        frame_seconds: f32,
+    ) -> Result<String, String> {
+        let (w, h) = options.size;
+        let n = self.frames.len().max(1);
+        let mut out = format!(
+            "<svg width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" xmlns=\"http://www.w3.org/2000/svg\">\n"
+        );
+        for i in 0..self.frames.len() {
+            let frame = self.frame_svg(i, options)?;
+            let (show, hide) = (i as f32 / n as f32, (i + 1) as f32 / n as f32);
+            writeln!(
+                out,
+                "<g visibility=\"hidden\"><animate attributeName=\"visibility\" values=\"hidden;visible;hidden\" \
+                 keyTimes=\"0;{show};{hide}\" dur=\"{}s\" calcMode=\"discrete\" repeatCount=\"indefinite\"/>",
+                n as f32 * frame_seconds
+            )
+            .unwrap();
+            out += strip_prolog(&frame);
+            out += "</g>\n";
+        }
+        out += "</svg>\n";
+        Ok(out)
+    }
+
+    /// Html page with all frames and a slider to step through them
+    pub fn to_html(&self, options: &PlotOptions) -> Result<String, String> {
+        let last = self.frames.len().saturating_sub(1);
+        let mut out = String::from(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>cut_space trace</title></head><body>\n",
+        );
+        writeln!(
+            out,
+            "<input type=\"range\" id=\"step\" min=\"0\" max=\"{last}\" value=\"0\" oninput=\"show(this.value)\"> <span id=\"caption\"></span>"
+        )
+        .unwrap();
+        for (i, f) in self.frames.iter().enumerate() {
+            let caption = match &f.step {
+                TraceStep::Query { query, status } => format!("{i}: query {query:?} -> {status:?}"),
+                TraceStep::Insert {
+                    new,
+                    intersected,
+                    produced,
+                } => format!(
+                    "{i}: insert {new:?}, {} fragments cut into {}",
+                    intersected.len(),
+                    produced.len()
+                ),
+            };
+            writeln!(
+                out,
+                "<div class=\"frame\" data-caption=\"{}\" style=\"display:none\">",
+                caption.replace('"', "&quot;")
+            )
+            .unwrap();
+            out += strip_prolog(&self.frame_svg(i, options)?);
+            out += "</div>\n";
+        }
+        out += "<script>\nconst frames = document.getElementsByClassName(\"frame\");\n\
+                function show(k) {\n\
+                  for (let i = 0; i < frames.length; i++) frames[i].style.display = i == k ? \"block\" : \"none\";\n\
+                  if (frames.length) document.getElementById(\"caption\").textContent = frames[k].dataset.caption;\n\
+                }\nshow(0);\n</script>\n</body></html>\n";
+        Ok(out)
+    }
+}
+
+/// Drops anything before the root element, so svg can be embedded
+#[cfg(feature = "plot")]
+fn strip_prolog(svg: &str) -> &str {
+    svg.find("<svg").map_or(svg, |i| &svg[i..])
+}
@@ -27,7 +27,7 @@ pub fn plotboxes(
             backend.draw_rect(lo, hi, &BLUE_50, true).unwrap();
             backend.draw_rect(lo, hi, &BLUE, false).unwrap();
             backend
-                .draw_text(&*i.to_string(), &style, lo)
+                .draw_text(&i.to_string(), &style, lo)
                 .expect("TODO: panic message")
         }
     }
@@ -39,7 +39,7 @@ pub fn plotboxes(
         if let Some((lo, hi)) = project_coords(*b) {
             backend.draw_rect(lo, hi, &RED, false).unwrap();
             backend
-                .draw_text(&*i.to_string(), &style, lo)
+                .draw_text(&i.to_string(), &style, lo)
                 .expect("TODO: panic message")
         }
     }
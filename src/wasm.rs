@@ -0,0 +1,114 @@
+//! JavaScript bindings, exported to JS as OcclusionBuffer.
+//! Batches pass boxes as flat Float32Array of x0, y0, x1, y1 quadruples.
+use wasm_bindgen::prelude::*;
+
+use crate::box_cutting::*;
+use crate::{BBox, OcclusionBuffer, OcclusionQuery, OcclusionStatus};
+
+#[wasm_bindgen(js_name = OcclusionBuffer)]
+#[derive(Clone)]
+pub struct WasmOcclusionBuffer {
+    buffer: OcclusionBuffer,
+}
+
+fn make_box(x0: f32, y0: f32, x1: f32, y1: f32) -> Result<BOX, JsError> {
+    match x0 < x1 && y0 < y1 {
+        true => Ok(BOX::new([x0, y0], [x1, y1])),
+        false => Err(JsError::new(&format!(
+            "Box [{x0}, {y0}] - [{x1}, {y1}] is empty or has NaN coordinates"
+        ))),
+    }
+}
+
+fn boxes(flat: &[f32]) -> Result<Vec<BOX>, JsError> {
+    if flat.len() % 4 != 0 {
+        return Err(JsError::new("Box array length should be a multiple of 4"));
+    }
+    flat.chunks_exact(4)
+        .map(|c| make_box(c[0], c[1], c[2], c[3]))
+        .collect()
+}
+
+#[wasm_bindgen(js_class = OcclusionBuffer)]
+impl WasmOcclusionBuffer {
+    /// strategy is one of "guillotine" (default), "vertical", "horizontal", "max_rect", "min_fragments"
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        x0: f32,
+        y0: f32,
+        x1: f32,
+        y1: f32,
+        strategy: Option<String>,
+    ) -> Result<WasmOcclusionBuffer, JsError> {
+        let strategy = match strategy {
+            Some(s) => s.parse().map_err(|e: String| JsError::new(&e))?,
+            None => SplitStrategy::default(),
+        };
+        Ok(Self {
+            buffer: OcclusionBuffer::with_strategy(make_box(x0, y0, x1, y1)?, strategy),
+        })
+    }
+
+    /// True if any part of the box is free
+    pub fn query(&mut self, x0: f32, y0: f32, x1: f32, y1: f32) -> Result<bool, JsError> {
+        let b = make_box(x0, y0, x1, y1)?;
+        Ok(self.buffer.query(&b) == OcclusionStatus::PartiallyVisible)
+    }
+
+    /// Marks the box as occupied
+    pub fn insert(&mut self, x0: f32, y0: f32, x1: f32, y1: f32) -> Result<(), JsError> {
+        self.buffer.insert(make_box(x0, y0, x1, y1)?);
+        Ok(())
+    }
+
+    /// Visibility of every box (1 visible, 0 occluded), buffer is not changed
+    #[wasm_bindgen(js_name = queryBatch)]
+    pub fn query_batch(&mut self, flat: &[f32]) -> Result<Vec<u8>, JsError> {
+        let boxes = boxes(flat)?;
+        Ok(boxes
+            .iter()
+            .map(|b| (self.buffer.query(b) == OcclusionStatus::PartiallyVisible) as u8)
+            .collect())
+    }
+
+    #[wasm_bindgen(js_name = insertBatch)]
+    pub fn insert_batch(&mut self, flat: &[f32]) -> Result<(), JsError> {
+        for b in boxes(flat)? {
+            self.buffer.insert(b);
+        }
+        Ok(())
+    }
+
+    /// Checks boxes front to back, inserting visible ones. Returns visibility like queryBatch.
+    #[wasm_bindgen(js_name = cullBatch)]
+    pub fn cull_batch(&mut self, flat: &[f32]) -> Result<Vec<u8>, JsError> {
+        let mut res = Vec::with_capacity(flat.len() / 4);
+        for b in boxes(flat)? {
+            let visible = self.buffer.query(&b) == OcclusionStatus::PartiallyVisible;
+            if visible {
+                self.buffer.insert(b);
+            }
+            res.push(visible as u8);
+        }
+        Ok(res)
+    }
+
+    /// Live free space fragments as flat Float32Array
+    #[wasm_bindgen(js_name = freeSpace)]
+    pub fn free_space(&self) -> Vec<f32> {
+        self.buffer
+            .free_boxes()
+            .flat_map(|b| [b.lo(0), b.lo(1), b.hi(0), b.hi(1)])
+            .collect()
+    }
+
+    /// Number of live free space fragments
+    pub fn fragments(&self) -> usize {
+        self.buffer.free_boxes().count()
+    }
+
+    /// Restores full free space
+    pub fn reset(&mut self) {
+        self.buffer.reset();
+    }
+}
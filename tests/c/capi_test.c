@@ -0,0 +1,70 @@
+/* Exercises the C API, run by tests/capi.rs. Exits with nonzero status on failure. */
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "aabb_occlusion_culling.h"
+
+#define CHECK(cond)                                                   \
+    do {                                                              \
+        if (!(cond)) {                                                \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__,    \
+                    __LINE__, #cond);                                 \
+            return 1;                                                 \
+        }                                                             \
+    } while (0)
+
+static AocBox box(float x0, float y0, float x1, float y1) {
+    AocBox b = {{x0, y0}, {x1, y1}};
+    return b;
+}
+
+int main(void) {
+    CHECK(aoc_buffer_new(box(0, 0, 1, 1), 42) == NULL);
+    CHECK(aoc_buffer_new(box(1, 0, 0, 1), AOC_STRATEGY_GUILLOTINE) == NULL);
+
+    AocBuffer *buf = aoc_buffer_new(box(0, 0, 10, 10), AOC_STRATEGY_MIN_FRAGMENTS);
+    CHECK(buf != NULL);
+
+    bool visible = false;
+    CHECK(aoc_query(buf, box(1, 1, 2, 2), &visible) == AOC_STATUS_OK);
+    CHECK(visible);
+    CHECK(aoc_insert(buf, box(0, 0, 5, 10)) == AOC_STATUS_OK);
+    CHECK(aoc_query(buf, box(1, 1, 2, 2), &visible) == AOC_STATUS_OK);
+    CHECK(!visible);
+    CHECK(aoc_insert(buf, box(2, 2, 1, 1)) == AOC_STATUS_INVALID_BOX);
+    CHECK(aoc_query(NULL, box(1, 1, 2, 2), &visible) == AOC_STATUS_NULL_POINTER);
+
+    AocBox batch[3] = {box(6, 1, 7, 2), box(6.2f, 1.2f, 6.8f, 1.8f), box(8, 8, 9, 9)};
+    bool vis[3];
+    CHECK(aoc_cull_batch(buf, batch, 3, vis) == AOC_STATUS_OK);
+    CHECK(vis[0] && !vis[1] && vis[2]);
+
+    size_t count = 0;
+    CHECK(aoc_free_space(buf, NULL, 0, &count) == AOC_STATUS_BUFFER_TOO_SMALL);
+    CHECK(count > 0);
+    AocBox *free_space = malloc(count * sizeof(AocBox));
+    size_t written = 0;
+    CHECK(aoc_free_space(buf, free_space, count, &written) == AOC_STATUS_OK);
+    CHECK(written == count);
+    float area = 0;
+    for (size_t i = 0; i < written; i++) {
+        area += (free_space[i].hi[0] - free_space[i].lo[0]) *
+                (free_space[i].hi[1] - free_space[i].lo[1]);
+    }
+    /* 100 minus left half and two unit boxes */
+    CHECK(area > 47.99f && area < 48.01f);
+    free(free_space);
+
+    CHECK(aoc_reset(buf) == AOC_STATUS_OK);
+    CHECK(aoc_free_space(buf, NULL, 0, &count) == AOC_STATUS_BUFFER_TOO_SMALL);
+    CHECK(count == 1);
+    CHECK(aoc_status_message(AOC_STATUS_PANIC)[0] != '\0');
+    CHECK(strcmp(aoc_status_message(42), "unknown status") == 0);
+    CHECK(strcmp(aoc_status_message(-1), "unknown status") == 0);
+
+    aoc_buffer_free(buf);
+    aoc_buffer_free(NULL);
+    printf("capi ok\n");
+    return 0;
+}
@@ -0,0 +1,72 @@
+//! Builds tests/c/capi_test.c against the cdylib and runs it
+#![cfg(feature = "capi")]
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Directory holding the shared library built for this test run
+fn library_dir() -> PathBuf {
+    let exe = std::env::current_exe().unwrap();
+    let deps = exe.parent().unwrap();
+    let is_lib = |p: &Path| {
+        p.file_name().and_then(|n| n.to_str()).is_some_and(|n| {
+            n.starts_with("libaabb_occlusion_culling.")
+                && (n.ends_with(".so") || n.ends_with(".dylib"))
+        })
+    };
+    let dir = [deps, deps.parent().unwrap()]
+        .into_iter()
+        .find(|d| {
+            std::fs::read_dir(d)
+                .map(|entries| entries.flatten().any(|e| is_lib(&e.path())))
+                .unwrap_or(false)
+        })
+        .expect("cdylib not found, build it with `cargo build --features capi`");
+    dir.to_owned()
+}
+
+/// build.rs writes the header into OUT_DIR, the checked-in copy must match it
+#[test]
+fn header_is_up_to_date() {
+    let generated = include_str!(concat!(env!("OUT_DIR"), "/aabb_occlusion_culling.h"));
+    let checked_in = std::fs::read_to_string(
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("include/aabb_occlusion_culling.h"),
+    )
+    .unwrap();
+    assert!(
+        generated == checked_in,
+        "include/aabb_occlusion_culling.h is stale, copy it from {}",
+        env!("OUT_DIR")
+    );
+}
+
+#[test]
+fn c_program() {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let lib_dir = library_dir();
+    let exe = Path::new(env!("CARGO_TARGET_TMPDIR")).join("capi_test");
+    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_owned());
+    let status = Command::new(&cc)
+        .arg(manifest.join("tests/c/capi_test.c"))
+        .arg("-I")
+        .arg(manifest.join("include"))
+        .arg("-L")
+        .arg(&lib_dir)
+        .arg("-laabb_occlusion_culling")
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .arg("-o")
+        .arg(&exe)
+        .status()
+        .unwrap_or_else(|e| panic!("Failed to run C compiler {cc}: {e}"));
+    assert!(status.success(), "C test program failed to compile");
+    // cargo's LD_LIBRARY_PATH may list a library built with other features before the rpath
+    let output = Command::new(&exe)
+        .env("LD_LIBRARY_PATH", &lib_dir)
+        .env("DYLD_LIBRARY_PATH", &lib_dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "C test program failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
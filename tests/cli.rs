@@ -0,0 +1,80 @@
+//! Runs occlusion-cli on the example scenes
+#![cfg(feature = "serde")]
+use std::process::{Command, Output};
+
+fn cli(scene: &str) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_occlusion-cli"))
+        .args([scene, "--no-free-space"])
+        .output()
+        .unwrap()
+}
+
+fn stdout(out: &Output) -> String {
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    String::from_utf8(out.stdout.clone()).unwrap()
+}
+
+#[test]
+fn example_json_scene() {
+    let out = stdout(&cli(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/scenes/example.json"
+    )));
+    assert!(
+        out.starts_with("# visibility\n1 visible\n2 occluded\n3 visible\n5 occluded\n100 occluded\n101 visible\n"),
+        "{out}"
+    );
+}
+
+#[test]
+fn example_csv_scene() {
+    let out = stdout(&cli(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/scenes/example.csv"
+    )));
+    assert!(
+        out.starts_with("# visibility\n0 visible\n1 occluded\n2 visible\n3 visible\n4 occluded\n"),
+        "{out}"
+    );
+}
+
+#[test]
+fn boxes_on_bounds_are_accepted() {
+    let path = std::env::temp_dir().join(format!("aoc_cli_touching_{}.csv", std::process::id()));
+    std::fs::write(&path, "0,0,10,10\n0,6,10,10\n0,0,10,10\n").unwrap();
+    let out = stdout(&cli(path.to_str().unwrap()));
+    assert!(
+        out.starts_with("# visibility\n0 visible\n1 visible\n"),
+        "{out}"
+    );
+    std::fs::write(&path, "0,0,10,10\n5,6,11,10\n").unwrap();
+    let out = cli(path.to_str().unwrap());
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(out.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("outside of scene bounds"));
+}
+
+#[test]
+fn csv_allows_one_header_row() {
+    let path = std::env::temp_dir().join(format!("aoc_cli_header_{}.csv", std::process::id()));
+    for (text, error) in [
+        ("x0,y0,x1,y1\nx0,y0,x1,y1\n0,0,10,10\n", "Line 2"),
+        ("x0,y0,x1,y1\n0,0,10,10\n1,1,2,oops\n", "Line 3"),
+        ("0,0,10,1O\n1,1,2,2\n", "Line 1"),
+        ("x0,1,x1,2\n0,0,10,10\n", "Line 1"),
+    ] {
+        std::fs::write(&path, text).unwrap();
+        let out = cli(path.to_str().unwrap());
+        assert_eq!(out.status.code(), Some(1), "{text}");
+        let stderr = String::from_utf8_lossy(&out.stderr).into_owned();
+        assert!(stderr.contains(error), "{text}: {stderr}");
+    }
+    std::fs::write(&path, "# comment\nx0,y0,x1,y1\n0,0,10,10\n1,1,2,2\n").unwrap();
+    let out = stdout(&cli(path.to_str().unwrap()));
+    std::fs::remove_file(&path).unwrap();
+    assert!(out.starts_with("# visibility\n0 visible\n"), "{out}");
+}
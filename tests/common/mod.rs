@@ -0,0 +1,27 @@
+//! Helpers shared by integration tests and benches (included there with #[path])
+#![allow(dead_code)]
+
+/// Tiny xorshift generator, so that sequences are reproducible from the seed alone.
+/// The state must not be zero, use new() to scramble arbitrary seeds.
+pub struct XorShift(pub u64);
+
+impl XorShift {
+    pub fn new(seed: u64) -> Self {
+        XorShift(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    pub fn uniform(&mut self, lo: f32, hi: f32) -> f32 {
+        lo + (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32 * (hi - lo)
+    }
+}
@@ -0,0 +1,87 @@
+//! Differential tests: random box sequences are replayed into OcclusionBuffer and a
+//! high resolution RasterBuffer, and both must agree on visibility and covered area.
+use aabb_occlusion_culling::*;
+
+mod common;
+use common::XorShift;
+
+/// Box coordinates are snapped to this grid, raster cells are aligned with it,
+/// so the raster answers are exact rather than approximate.
+const GRID: f32 = 1.0 / 16.0;
+const CELLS_PER_GRID: usize = 4;
+const AREA_TOLERANCE: f32 = 1e-3;
+
+/// Random box within [0, 1] with grid-aligned sides, often sharing coordinates with previous ones
+fn random_box(rng: &mut XorShift) -> BOX {
+    let mut axis = || {
+        let a = rng.below(17);
+        let mut b = rng.below(16);
+        if b >= a {
+            b += 1;
+        }
+        (a.min(b) as f32 * GRID, a.max(b) as f32 * GRID)
+    };
+    let (x0, x1) = axis();
+    let (y0, y1) = axis();
+    BOX::new([x0, y0], [x1, y1])
+}
+
+fn replay(strategy: SplitStrategy, seed: u64, len: usize) {
+    let bounds = BOX::new([-1.0, -1.0], [2.0, 2.0]);
+    let cells = (3.0 / GRID) as usize * CELLS_PER_GRID;
+    let mut exact = OcclusionBuffer::with_strategy(bounds, strategy);
+    let mut raster = RasterBuffer::new(bounds, cells, cells);
+    let mut rng = XorShift::new(seed);
+    let mut history = vec![];
+
+    for _ in 0..len {
+        let newbox = random_box(&mut rng);
+        history.push(newbox);
+
+        let expected = raster.query(&newbox);
+        let status = exact.check_a_box(newbox);
+        assert_eq!(
+            status, expected,
+            "Visibility mismatch for {strategy:?}, seed {seed}, sequence {history:?}"
+        );
+        if status == OcclusionStatus::PartiallyVisible {
+            exact.add_last_box();
+        }
+        raster.insert(newbox);
+
+        let exact_free: f32 = exact.free_boxes().map(|b| b.area()).sum();
+        let raster_free = bounds.area() - raster.covered_area();
+        assert!(
+            (exact_free - raster_free).abs() < AREA_TOLERANCE,
+            "Free area {exact_free} differs from reference {raster_free} for {strategy:?}, seed {seed}, sequence {history:?}"
+        );
+    }
+}
+
+#[test]
+pub fn differential_random_sequences() {
+    for strategy in SplitStrategy::ALL {
+        for seed in 0..20 {
+            replay(strategy, seed, 16);
+        }
+    }
+}
+
+/// New box exactly matching a fragment used to hit "visit a doctor" in cut_space
+#[test]
+pub fn differential_exact_fragment() {
+    let bounds = BOX::new([-1.0, -1.0], [2.0, 2.0]);
+    let mut exact = OcclusionBuffer::new(bounds);
+    let mut raster = RasterBuffer::new(bounds, 192, 192);
+    for newbox in [
+        BOX::new([0.0, 0.0], [1.0, 1.0]),
+        BOX::new([0.0, -1.0], [1.0, 0.0]),
+        BOX::new([-1.0, -1.0], [0.0, 0.5]),
+    ] {
+        assert_eq!(exact.query(&newbox), raster.query(&newbox));
+        exact.insert(newbox);
+        raster.insert(newbox);
+    }
+    let exact_free: f32 = exact.free_boxes().map(|b| b.area()).sum();
+    assert!((exact_free - (bounds.area() - raster.covered_area())).abs() < AREA_TOLERANCE);
+}
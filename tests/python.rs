@@ -0,0 +1,303 @@
+//! Runs Python snippets against the bindings in an embedded interpreter
+#![cfg(feature = "python")]
+use aabb_occlusion_culling::{PyBox, PyCullIter, PyOcclusionBuffer, PyScene};
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+use pyo3::types::{IntoPyDict, PyDict};
+
+/// Globals with the bindings imported as aoc
+fn aoc_globals(py: Python<'_>) -> &PyDict {
+    let aoc = PyModule::new(py, "aabb_occlusion_culling").unwrap();
+    aoc.add_class::<PyBox>().unwrap();
+    aoc.add_class::<PyOcclusionBuffer>().unwrap();
+    aoc.add_class::<PyCullIter>().unwrap();
+    aoc.add_class::<PyScene>().unwrap();
+    [("aoc", aoc)].into_py_dict(py)
+}
+
+/// Runs code in globals, printing the traceback on failure
+fn run_in(py: Python<'_>, globals: &PyDict, code: &str) {
+    if let Err(e) = py.run(code, Some(globals), None) {
+        e.print(py);
+        panic!("Python code failed");
+    }
+}
+
+/// Runs code with the bindings imported as aoc
+fn run_python(code: &str) {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| run_in(py, aoc_globals(py), code))
+}
+
+#[test]
+fn box_class() {
+    run_python(
+        r#"
+b = aoc.Box(1, 2, 3, 6)
+assert (b.x0, b.y0, b.x1, b.y1) == (1, 2, 3, 6)
+assert list(b.lo) == [1, 2] and list(b.hi) == [3, 6]
+assert (b.width, b.height, b.area) == (2, 4, 8)
+assert tuple(map(tuple, b.to_tuple())) == ((1, 2), (3, 6))
+assert repr(b) == "Box(1.0, 2.0, 3.0, 6.0)", repr(b)
+assert b == aoc.Box(1, 2, 3, 6) and b != aoc.Box(1, 2, 3, 5)
+assert b != ((1, 2), (3, 6))
+b.x1 = 5
+assert b.width == 4
+
+buf = aoc.PyOcclusionBuffer((0, 0), (10, 10))
+assert buf.check_a_box(b)
+buf.add_last_box()
+assert not buf.check_a_box(aoc.Box(1.5, 2.5, 4, 5))
+assert buf.check_a_box(((1.5, 2.5), (6, 5)))
+"#,
+    );
+}
+
+#[test]
+fn copies_and_len() {
+    run_python(
+        r#"
+import copy
+
+buf = aoc.PyOcclusionBuffer((0, 0), (4, 4))
+assert len(buf) == 1
+assert tuple(map(tuple, buf.bounds)) == ((0, 0), (4, 4))
+assert buf.strategy == "guillotine"
+payload = {"id": 1}
+buf.add_box_set([((1, 1), (2, 2))], [payload])
+assert len(buf) == 4
+assert "fragments=4" in repr(buf)
+
+shallow = copy.copy(buf)
+assert shallow is not buf and len(shallow) == 4
+assert shallow.occluders()[0][1] is payload
+deep = copy.deepcopy(buf)
+assert deep.occluders()[0][1] == payload and deep.occluders()[0][1] is not payload
+
+# copies are independent of the original
+shallow.add_box_set([((2.5, 2.5), (3, 3))])
+assert len(buf) == 4 and len(shallow) > 4
+"#,
+    );
+}
+
+#[test]
+fn deepcopy_with_payload_referring_to_buffer() {
+    run_python(
+        r#"
+import copy
+
+buf = aoc.PyOcclusionBuffer((0, 0), (4, 4))
+payload = []
+assert buf.check_a_box(((1, 1), (2, 2)))
+buf.add_last_box(payload)
+payload.append(buf)
+deep = copy.deepcopy(buf)
+copied = deep.occluders()[0][1]
+assert copied is not payload
+assert copied[0] is deep
+"#,
+    );
+}
+
+/// Boxes for cull_iter tests: a generator counting how many boxes were pulled,
+/// and the expected visibility from culling the same boxes one by one
+const CULL_ITER_SETUP: &str = r#"
+boxes = [((i, 1), (i + 0.5, 2)) for i in range(1, 8)]
+boxes[3] = boxes[1]
+pulled = 0
+
+def gen():
+    global pulled
+    for b in boxes:
+        pulled += 1
+        yield b
+
+ref = aoc.PyOcclusionBuffer((0, 0), (10, 10))
+expected = []
+for b in boxes:
+    expected.append(ref.check_a_box(b))
+    if expected[-1]:
+        ref.add_last_box()
+assert expected == [True, True, True, False, True, True, True]
+buf = aoc.PyOcclusionBuffer((0, 0), (10, 10))
+"#;
+
+#[test]
+fn cull_iter_chunk_boundaries() {
+    run_python(&format!(
+        "{CULL_ITER_SETUP}{}",
+        r#"
+it = buf.cull_iter(gen(), chunk_size=3)
+got = []
+for n in range(7):
+    got.append(next(it))
+    # boxes are pulled a whole chunk at a time, the last chunk is short
+    assert pulled == min(7, (n // 3 + 1) * 3), (n, pulled)
+assert got == list(enumerate(expected))
+assert next(it, None) is None
+assert buf.free_space() == ref.free_space()
+"#
+    ));
+}
+
+#[test]
+fn cull_iter_chunk_size_not_dividing_input() {
+    run_python(&format!(
+        "{CULL_ITER_SETUP}{}",
+        r#"
+for chunk_size in (1, 2, 4, 6, 7, 100):
+    buf = aoc.PyOcclusionBuffer((0, 0), (10, 10))
+    assert list(buf.cull_iter(boxes, chunk_size)) == list(enumerate(expected)), chunk_size
+    assert buf.free_space() == ref.free_space()
+try:
+    buf.cull_iter(boxes, 0)
+    assert False
+except ValueError:
+    pass
+"#
+    ));
+}
+
+#[test]
+fn cull_iter_extract_failing_mid_chunk() {
+    run_python(&format!(
+        "{CULL_ITER_SETUP}{}",
+        r#"
+it = buf.cull_iter(boxes[:2] + ["not a box"] + boxes[2:], chunk_size=4)
+# boxes pulled before the failing one are culled and yielded first
+assert next(it) == (0, True)
+assert next(it) == (1, True)
+assert len(buf.free_space()) > 1
+try:
+    next(it)
+    assert False
+except TypeError:
+    pass
+# the iterator is finished after raising
+assert next(it, None) is None
+
+def failing():
+    yield boxes[0]
+    raise KeyError("source failed")
+
+buf = aoc.PyOcclusionBuffer((0, 0), (10, 10))
+it = buf.cull_iter(failing(), chunk_size=4)
+assert next(it) == (0, True)
+try:
+    next(it)
+    assert False
+except KeyError:
+    pass
+assert list(it) == []
+"#
+    ));
+}
+
+#[test]
+fn cull_iter_buffer_borrowed() {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let globals = aoc_globals(py);
+        run_in(py, globals, CULL_ITER_SETUP);
+        run_in(py, globals, "it = buf.cull_iter(gen(), chunk_size=3)");
+        let buf: &PyCell<PyOcclusionBuffer> = globals.get_item("buf").unwrap().downcast().unwrap();
+        let borrowed = buf.borrow();
+        let err = py.run("next(it)", Some(globals), None).unwrap_err();
+        assert!(err.is_instance_of::<PyRuntimeError>(py));
+        drop(borrowed);
+        // the chunk pulled before the failed borrow is culled on the next call
+        run_in(
+            py,
+            globals,
+            r#"
+assert pulled == 3
+assert list(it) == list(enumerate(expected))
+assert buf.free_space() == ref.free_space()
+"#,
+        );
+    })
+}
+
+#[test]
+fn payloads() {
+    run_python(
+        r#"
+def boxes(occluders):
+    return [(tuple(lo) + tuple(hi), p) for (lo, hi), p in occluders]
+
+buf = aoc.PyOcclusionBuffer((0, 0), (10, 10))
+assert buf.check_a_box(((1, 1), (4, 4)))
+buf.add_last_box("house")
+# occluded boxes are not added and their payloads are dropped
+buf.add_box_set(
+    [((3, 3), (8, 5)), ((2, 2), (3, 3)), aoc.Box(6, 6, 9, 9)],
+    ["wall", "hidden", {"sign": 1}],
+)
+# boxes without payloads are added but not remembered
+buf.add_box_set([((6, 1), (7, 2))])
+assert boxes(buf.occluders()) == [
+    ((1, 1, 4, 4), "house"),
+    ((3, 3, 8, 5), "wall"),
+    ((6, 6, 9, 9), {"sign": 1}),
+]
+try:
+    buf.add_box_set([((1, 6), (2, 7))], ["a", "b"])
+    assert False
+except ValueError:
+    pass
+assert buf.check_a_box(((1, 6), (2, 7)))
+
+assert buf.covering(((3.5, 3.5), (4.5, 4.5))) == ["house", "wall"]
+assert buf.covering(aoc.Box(8.5, 8.5, 9.5, 9.5)) == [{"sign": 1}]
+assert buf.covering(((6, 1), (7, 2))) == []
+assert boxes(buf.occluders(((0, 0), (5, 5)))) == [((1, 1, 4, 4), "house")]
+assert [p for _, p in buf.occluders(aoc.Box(0, 0, 10, 6))] == ["house", "wall"]
+assert buf.occluders(((0, 6), (5, 10))) == []
+
+# occluders are clipped to bounds, those leaving them are dropped
+buf.translate((-4, 0))
+assert boxes(buf.occluders()) == [((0, 3, 4, 5), "wall"), ((2, 6, 5, 9), {"sign": 1})]
+buf.scale((0, 0), (2.5, 1))
+assert boxes(buf.occluders()) == [((0, 3, 10, 5), "wall"), ((5, 6, 10, 9), {"sign": 1})]
+buf.set_bounds((0, 0), (10, 5.5))
+assert boxes(buf.occluders()) == [((0, 3, 10, 5), "wall")]
+buf.set_bounds((2, 0), (12, 5.5))
+assert boxes(buf.occluders()) == [((2, 3, 10, 5), "wall")]
+buf.reset()
+assert buf.occluders() == []
+"#,
+    );
+}
+
+#[test]
+fn stub_matches_module() {
+    let stub = concat!(env!("CARGO_MANIFEST_DIR"), "/aabb_occlusion_culling.pyi");
+    run_python(&format!(
+        "stub_path = {stub:?}\n{}",
+        r#"
+import ast
+
+with open(stub_path) as f:
+    tree = ast.parse(f.read())
+stub = {}
+for node in tree.body:
+    if isinstance(node, ast.ClassDef):
+        stub[node.name] = {
+            n.name if isinstance(n, ast.FunctionDef) else n.target.id
+            for n in node.body
+            if isinstance(n, (ast.FunctionDef, ast.AnnAssign))
+        }
+classes = {name: cls for name, cls in vars(aoc).items() if isinstance(cls, type)}
+assert set(stub) == set(classes), (set(stub), set(classes))
+for name, members in stub.items():
+    cls = classes[name]
+    public = {m for m in members if not m.startswith("_")}
+    defined = {m for m in vars(cls) if not m.startswith("_")}
+    assert public == defined, (name, public ^ defined)
+    for m in members - public:
+        implemented = "__new__" if m == "__init__" else m
+        assert getattr(cls, implemented) is not getattr(object, implemented, None), (name, m)
+"#
+    ));
+}
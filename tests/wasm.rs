@@ -0,0 +1,50 @@
+//! JavaScript bindings, run with `wasm-pack test --node --features wasm`
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+use wasm_bindgen_test::*;
+
+use aabb_occlusion_culling::WasmOcclusionBuffer;
+use wasm_bindgen::JsError;
+
+fn ok<T>(r: Result<T, JsError>) -> T {
+    r.unwrap_or_else(|_| panic!("Unexpected JsError"))
+}
+
+#[wasm_bindgen_test]
+fn query_and_insert() {
+    let mut ob = ok(WasmOcclusionBuffer::new(0.0, 0.0, 10.0, 10.0, None));
+    assert!(ok(ob.query(1.0, 1.0, 3.0, 3.0)));
+    ok(ob.insert(0.0, 0.0, 5.0, 10.0));
+    assert!(!ok(ob.query(1.0, 1.0, 3.0, 3.0)));
+    assert!(ok(ob.query(4.0, 1.0, 6.0, 3.0)));
+    let free = ob.free_space();
+    assert_eq!(free.len(), 4 * ob.fragments());
+    let area: f32 = free.chunks(4).map(|c| (c[2] - c[0]) * (c[3] - c[1])).sum();
+    assert_eq!(area, 50.0);
+    ob.reset();
+    assert_eq!(ob.fragments(), 1);
+}
+
+#[wasm_bindgen_test]
+fn batches() {
+    let mut ob = ok(WasmOcclusionBuffer::new(
+        0.0,
+        0.0,
+        10.0,
+        10.0,
+        Some("vertical".to_owned()),
+    ));
+    let boxes = [0.0, 0.0, 6.0, 6.0, 1.0, 1.0, 2.0, 2.0, 5.0, 5.0, 8.0, 8.0];
+    assert_eq!(ok(ob.query_batch(&boxes)), vec![1, 1, 1]);
+    assert_eq!(ok(ob.cull_batch(&boxes)), vec![1, 0, 1]);
+    ok(ob.insert_batch(&[0.0, 0.0, 10.0, 10.0]));
+    assert_eq!(ob.fragments(), 0);
+}
+
+#[wasm_bindgen_test]
+fn invalid_input() {
+    assert!(WasmOcclusionBuffer::new(0.0, 0.0, 10.0, 10.0, Some("nope".to_owned())).is_err());
+    assert!(WasmOcclusionBuffer::new(5.0, 0.0, 1.0, 10.0, None).is_err());
+    let mut ob = ok(WasmOcclusionBuffer::new(0.0, 0.0, 10.0, 10.0, None));
+    assert!(ob.query(f32::NAN, 0.0, 1.0, 1.0).is_err());
+    assert!(ob.cull_batch(&[0.0, 0.0, 1.0]).is_err());
+}